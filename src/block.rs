@@ -1,15 +1,17 @@
 use std::prelude::v1::*;
 
 use super::{
-    deserialize_u64, parse_string_h256, parse_string_u64, serialize_u64_hex, Nilable, Receipt,
-    Transaction, TransactionInner, SH160, SH256, SU256, SU64,
+    capped_gas_price, deserialize_u64, parse_string_h256, parse_string_u64, serialize_data,
+    serialize_u64_hex, Log, Nilable, Receipt, Transaction, TransactionInner, SH160, SH256, SU256,
+    SU64,
 };
 use crypto::keccak_hash;
 use ethereum_types::U64;
 use hash256_std_hasher::Hash256StdHasher;
 use hex::HexBytes;
-use serde::de::DeserializeOwned;
-use serde::{Deserialize, Serialize, Serializer};
+use serde::de::{DeserializeOwned, Error};
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use std::collections::HashMap;
 use std::iter::Iterator;
 use std::sync::Arc;
 
@@ -48,12 +50,14 @@ pub struct BlockHeader {
     pub state_root: SH256,
     pub transactions_root: SH256,
     pub receipts_root: SH256,
+    #[serde(serialize_with = "serialize_data")]
     pub logs_bloom: HexBytes,
     pub difficulty: SU256,
     pub number: SU64,
     pub gas_limit: SU64,
     pub gas_used: SU64,
     pub timestamp: SU64,
+    #[serde(serialize_with = "serialize_data")]
     pub extra_data: HexBytes,
     pub mix_hash: SH256,
     pub nonce: BlockNonce,
@@ -137,6 +141,26 @@ impl BlockHeader {
         hash.as_bytes_mut().copy_from_slice(&keccak_hash(&data));
         return hash;
     }
+
+    pub fn bloom(&self) -> Result<Bloom, String> {
+        if self.logs_bloom.len() != 256 {
+            return Err(format!(
+                "invalid logs bloom length, want: 256, got: {}",
+                self.logs_bloom.len()
+            ));
+        }
+        let mut bloom = Bloom::new();
+        bloom.0.copy_from_slice(&self.logs_bloom);
+        Ok(bloom)
+    }
+
+    pub fn set_bloom(&mut self, bloom: &Bloom) {
+        self.logs_bloom = bloom.to_hex();
+    }
+
+    pub fn set_logs_bloom_from_receipts(&mut self, receipts: &[Receipt]) {
+        self.set_bloom(&create_bloom(receipts.iter()));
+    }
 }
 
 pub trait BlockTrait: Clone + DeserializeOwned {}
@@ -185,13 +209,17 @@ impl BlockHeaderTrait for BlockHeader {
 
 #[derive(Clone, Debug, Deserialize, Serialize, PartialEq, Eq)]
 #[serde(rename_all = "camelCase")]
-pub struct Block {
+pub struct GenericBlock<H, T, W> {
     #[serde(flatten)]
-    pub header: BlockHeader,
-    pub transactions: Vec<Transaction>,
-    pub withdrawals: Option<Vec<Withdrawal>>, // rlp: optional
+    pub header: H,
+    pub transactions: Vec<T>,
+    pub withdrawals: Option<Vec<W>>, // rlp: optional
 }
 
+pub type Block = GenericBlock<BlockHeader, Transaction, Withdrawal>;
+
+pub type BlockSimple<H, W> = GenericBlock<H, SH256, W>;
+
 impl BlockTrait for Block {}
 
 #[derive(Clone, Debug, Deserialize, Serialize, Default, PartialEq, Eq)]
@@ -214,13 +242,18 @@ impl rlp::Encodable for Withdrawal {
     }
 }
 
-#[derive(Clone, Debug, Deserialize, Serialize, PartialEq, Eq)]
-#[serde(rename_all = "camelCase")]
-pub struct BlockSimple<H, W> {
-    #[serde(flatten)]
-    pub header: H,
-    pub transactions: Vec<SH256>,
-    pub withdrawals: Option<Vec<W>>, // rlp: optional
+impl rlp::Decodable for Withdrawal {
+    fn decode(rlp: &rlp::Rlp) -> Result<Self, rlp::DecoderError> {
+        if rlp.item_count()? != 4 {
+            return Err(rlp::DecoderError::RlpIncorrectListLen);
+        }
+        Ok(Self {
+            index: rlp.val_at(0)?,
+            validator_index: rlp.val_at(1)?,
+            address: rlp.val_at(2)?,
+            amount: rlp.val_at(3)?,
+        })
+    }
 }
 
 impl Block {
@@ -242,7 +275,7 @@ impl Block {
         if receipts.len() == 0 {
             header.receipts_root = empty_root_hash.clone();
         } else {
-            header.logs_bloom = create_bloom(receipts.iter()).to_hex();
+            header.set_logs_bloom_from_receipts(receipts);
             let rs: Vec<_> = receipts.iter().map(|r| r.rlp_bytes()).collect();
             header.receipts_root = triehash::ordered_trie_root::<KeccakHasher, _>(rs).into();
         }
@@ -263,6 +296,70 @@ impl Block {
             withdrawals,
         }
     }
+
+    // keccak over the concatenated tx hashes. Unlike the transactions root it
+    // doesn't need a trie, and any reordering changes it.
+    pub fn tx_order_hash(&self) -> SH256 {
+        let mut data = Vec::with_capacity(self.transactions.len() * 32);
+        for tx in &self.transactions {
+            data.extend_from_slice(tx.hash.as_bytes());
+        }
+        keccak_hash(&data).into()
+    }
+
+    pub fn effective_gas_price(&self, tx_index: usize) -> Option<SU256> {
+        let tx = self.transactions.get(tx_index)?;
+        match tx.r#type.as_u64() {
+            2 => {
+                let max_fee = tx.max_fee_per_gas?;
+                let tip = tx.max_priority_fee_per_gas?;
+                Some(capped_gas_price(&max_fee, &tip, self.header.base_fee()))
+            }
+            _ => tx.gas_price,
+        }
+    }
+
+    pub fn build_index(&self) -> HashMap<SH256, usize> {
+        self.transactions
+            .iter()
+            .enumerate()
+            .map(|(idx, tx)| (tx.hash, idx))
+            .collect()
+    }
+
+    // The signed, EIP-2718 encoded transactions, e.g. for rebroadcasting.
+    pub fn raw_transactions(&self) -> Result<Vec<Vec<u8>>, String> {
+        let mut out = Vec::with_capacity(self.transactions.len());
+        for (idx, tx) in self.transactions.iter().enumerate() {
+            let inner = match tx.clone().inner() {
+                Some(inner) => inner,
+                None => {
+                    return Err(format!(
+                        "[tx.{}] missing fields for tx type {}: {:?}",
+                        idx, tx.r#type, tx.hash
+                    ))
+                }
+            };
+            out.push(inner.to_bytes());
+        }
+        Ok(out)
+    }
+
+    pub fn validate_indices(&self) -> Result<(), String> {
+        for (idx, tx) in self.transactions.iter().enumerate() {
+            match tx.transaction_index {
+                Some(index) if index.as_u64() == idx as u64 => {}
+                Some(index) => {
+                    return Err(format!(
+                        "[tx.{}] unexpected transaction index: {}",
+                        idx, index
+                    ))
+                }
+                None => return Err(format!("[tx.{}] missing transaction index", idx)),
+            }
+        }
+        Ok(())
+    }
 }
 
 pub fn withdrawal_root(withdrawals: &[Withdrawal]) -> SH256 {
@@ -274,23 +371,127 @@ pub fn withdrawal_root(withdrawals: &[Withdrawal]) -> SH256 {
     }
 }
 
+// Estimates the length of the `[header, txs, uncles, withdrawals]` block rlp
+// without building the block. Typed transactions are wrapped as rlp strings.
+pub fn estimate_block_size(
+    header: &BlockHeader,
+    txs: &[TransactionInner],
+    withdrawals: Option<&[Withdrawal]>,
+) -> usize {
+    let mut txs_len = 0;
+    for tx in txs {
+        let len = tx.to_bytes().len();
+        txs_len += match tx {
+            TransactionInner::Legacy(_) => len,
+            _ => rlp_header_len(len) + len,
+        };
+    }
+
+    let mut payload_len = rlp::encode(header).len();
+    payload_len += rlp_header_len(txs_len) + txs_len;
+    payload_len += rlp_header_len(0); // uncles
+    if let Some(withdrawals) = withdrawals {
+        let wd_len: usize = withdrawals.iter().map(|wd| rlp::encode(wd).len()).sum();
+        payload_len += rlp_header_len(wd_len) + wd_len;
+    }
+    rlp_header_len(payload_len) + payload_len
+}
+
+fn rlp_header_len(payload_len: usize) -> usize {
+    if payload_len < 56 {
+        return 1;
+    }
+    let len_bytes = (usize::BITS - payload_len.leading_zeros() + 7) / 8;
+    1 + len_bytes as usize
+}
+
+// Splits a `[header, txs, uncles, ..]` block rlp into the raw header item
+// and the raw bytes of the remaining body items.
+pub fn split_block_rlp(data: &[u8]) -> Result<(&[u8], &[u8]), rlp::DecoderError> {
+    let block = rlp::Rlp::new(data);
+    if !block.is_list() {
+        return Err(rlp::DecoderError::RlpExpectedToBeList);
+    }
+    let info = block.payload_info()?;
+    let end = info.header_len + info.value_len;
+    if end > data.len() {
+        return Err(rlp::DecoderError::RlpIsTooShort);
+    }
+    let payload = &data[info.header_len..end];
+
+    let header = rlp::Rlp::new(payload);
+    if !header.is_list() {
+        return Err(rlp::DecoderError::RlpExpectedToBeList);
+    }
+    let info = header.payload_info()?;
+    let header_end = info.header_len + info.value_len;
+    if header_end > payload.len() {
+        return Err(rlp::DecoderError::RlpIsTooShort);
+    }
+    Ok((&payload[..header_end], &payload[header_end..]))
+}
+
 pub fn create_bloom<'a>(receipts: impl Iterator<Item = &'a Receipt>) -> Bloom {
-    let mut buf = [0_u8; 6];
     let mut bin = Bloom::new();
     for receipt in receipts {
-        for log in &receipt.logs {
-            bin.add(&log.address.raw().0[..], &mut buf);
-            for b in &log.topics {
-                bin.add(&b.raw().0[..], &mut buf);
-            }
-        }
+        bin.accrue(receipt);
     }
     return bin;
 }
 
-#[derive(Debug)]
+pub fn range_bloom(receipts: &[Receipt]) -> Bloom {
+    create_bloom(receipts.iter())
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
 pub struct Bloom([u8; 256]);
 
+impl Serialize for Bloom {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        serialize_data(&self.0, serializer)
+    }
+}
+
+impl<'de> Deserialize<'de> for Bloom {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        let s: String = Deserialize::deserialize(deserializer)?;
+        let data = hex::decode(s.trim_start_matches("0x")).map_err(Error::custom)?;
+        if data.len() != 256 {
+            return Err(Error::custom(format!(
+                "invalid bloom length, want: 256, got: {}",
+                data.len()
+            )));
+        }
+        let mut bloom = Bloom::new();
+        bloom.0.copy_from_slice(&data);
+        Ok(bloom)
+    }
+}
+
+impl rlp::Encodable for Bloom {
+    fn rlp_append(&self, s: &mut rlp::RlpStream) {
+        (&self.0[..]).rlp_append(s)
+    }
+}
+
+impl rlp::Decodable for Bloom {
+    fn decode(rlp: &rlp::Rlp) -> Result<Self, rlp::DecoderError> {
+        let tmp: Vec<u8> = rlp.as_val()?;
+        if tmp.len() != 256 {
+            return Err(rlp::DecoderError::RlpInvalidLength);
+        }
+        let mut bloom = Bloom::new();
+        bloom.0.copy_from_slice(&tmp);
+        Ok(bloom)
+    }
+}
+
 impl Default for Bloom {
     fn default() -> Self {
         Self(unsafe { std::mem::zeroed() })
@@ -329,6 +530,35 @@ impl Bloom {
         self.0[i3] |= v3;
     }
 
+    pub fn contains(&self, d: &[u8]) -> bool {
+        let mut buf = [0_u8; 6];
+        let (i1, v1, i2, v2, i3, v3) = Self::bloom_values(d, &mut buf);
+        (self.0[i1] & v1) == v1 && (self.0[i2] & v2) == v2 && (self.0[i3] & v3) == v3
+    }
+
+    pub fn bits_set(&self) -> u32 {
+        self.0.iter().map(|b| b.count_ones()).sum()
+    }
+
+    // Fraction of the 2048 bits that are set, only meaningful for metrics.
+    pub fn saturation(&self) -> f64 {
+        self.bits_set() as f64 / (self.0.len() * 8) as f64
+    }
+
+    pub fn accrue(&mut self, receipt: &Receipt) {
+        self.add_logs(&receipt.logs);
+    }
+
+    pub fn add_logs(&mut self, logs: &[Log]) {
+        let mut buf = [0_u8; 6];
+        for log in logs {
+            self.add(&log.address.raw().0[..], &mut buf);
+            for b in &log.topics {
+                self.add(&b.raw().0[..], &mut buf);
+            }
+        }
+    }
+
     fn bloom_values(data: &[u8], hashbuf: &mut [u8; 6]) -> (usize, u8, usize, u8, usize, u8) {
         use tiny_keccak::{Hasher, Keccak};
         const BLOOM_BYTE_LENGTH: usize = 256;
@@ -362,11 +592,15 @@ impl hash_db::Hasher for KeccakHasher {
     }
 }
 
-#[derive(Clone, Copy, Debug)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
 pub enum BlockSelector {
     Number(SU64),
     Hash(SH256),
     Latest,
+    Earliest,
+    Pending,
+    Safe,
+    Finalized,
 }
 
 impl From<u64> for BlockSelector {
@@ -381,26 +615,51 @@ impl From<SU64> for BlockSelector {
     }
 }
 
+impl From<SU256> for BlockSelector {
+    fn from(val: SU256) -> Self {
+        Self::Number(val.as_u64().into())
+    }
+}
+
 impl From<SH256> for BlockSelector {
     fn from(val: SH256) -> Self {
         Self::Hash(val)
     }
 }
 
-impl From<String> for BlockSelector {
-    fn from(val: String) -> Self {
-        if val.as_str() == "latest" {
-            return Self::Latest;
+impl BlockSelector {
+    pub fn as_number(&self) -> Option<SU64> {
+        match self {
+            Self::Number(n) => Some(*n),
+            _ => None,
+        }
+    }
+
+    pub fn parse(val: &str) -> Result<Self, String> {
+        match val {
+            "latest" => return Ok(Self::Latest),
+            "earliest" => return Ok(Self::Earliest),
+            "pending" => return Ok(Self::Pending),
+            "safe" => return Ok(Self::Safe),
+            "finalized" => return Ok(Self::Finalized),
+            _ => {}
         }
         if !val.starts_with("0x") {
-            return parse_string_u64(&val).unwrap().as_u64().into();
+            let n = parse_string_u64(val).map_err(|err| format!("{:?}: {:?}", val, err))?;
+            return Ok(Self::Number(n.into()));
         }
-        let data = HexBytes::from_hex(val.as_bytes()).unwrap();
-        if data.len() == 32 {
-            let hash = parse_string_h256(&val).unwrap().into();
-            return Self::Hash(hash);
+        if val.len() == 66 {
+            let hash = parse_string_h256(val).map_err(|err| format!("{:?}: {:?}", val, err))?;
+            return Ok(Self::Hash(hash.into()));
         }
-        return Self::Number(parse_string_u64(&val).unwrap().as_u64().into());
+        let n = parse_string_u64(val).map_err(|err| format!("{:?}: {:?}", val, err))?;
+        Ok(Self::Number(n.into()))
+    }
+}
+
+impl From<String> for BlockSelector {
+    fn from(val: String) -> Self {
+        Self::parse(&val).unwrap()
     }
 }
 
@@ -413,147 +672,901 @@ impl Serialize for BlockSelector {
             Self::Hash(n) => format!("0x{}", hex::encode(&n.0)),
             Self::Number(n) => format!("0x{:x}", n.raw()),
             Self::Latest => "latest".into(),
+            Self::Earliest => "earliest".into(),
+            Self::Pending => "pending".into(),
+            Self::Safe => "safe".into(),
+            Self::Finalized => "finalized".into(),
         };
         serializer.serialize_str(&val)
     }
 }
 
+impl<'de> Deserialize<'de> for BlockSelector {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        let s: String = Deserialize::deserialize(deserializer)?;
+        Self::parse(&s).map_err(Error::custom)
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct FieldMismatch {
+    pub field: String,
+    // `None` on one side means the item only exists on the other side
+    pub want: Option<serde_json::Value>,
+    pub got: Option<serde_json::Value>,
+}
+
+fn to_json_value<T: Serialize>(val: &T) -> serde_json::Value {
+    serde_json::to_value(val).unwrap_or(serde_json::Value::Null)
+}
+
+impl FieldMismatch {
+    pub fn new<T: Serialize>(field: String, want: &T, got: &T) -> Self {
+        Self {
+            field,
+            want: Some(to_json_value(want)),
+            got: Some(to_json_value(got)),
+        }
+    }
+
+    pub fn missing<T: Serialize>(field: String, want: &T) -> Self {
+        Self {
+            field,
+            want: Some(to_json_value(want)),
+            got: None,
+        }
+    }
+
+    pub fn unexpected<T: Serialize>(field: String, got: &T) -> Self {
+        Self {
+            field,
+            want: None,
+            got: Some(to_json_value(got)),
+        }
+    }
+}
+
+impl std::fmt::Display for FieldMismatch {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match (&self.want, &self.got) {
+            (Some(want), Some(got)) => write!(
+                f,
+                "[{}] not match, want: {}, got: {}",
+                self.field, want, got
+            ),
+            (Some(want), None) => write!(f, "[{}] missing {}", self.field, want),
+            (None, Some(got)) => write!(f, "[{}] unexpected {}", self.field, got),
+            (None, None) => write!(f, "[{}] not match", self.field),
+        }
+    }
+}
+
+fn join_mismatches(list: Vec<FieldMismatch>) -> String {
+    let list: Vec<String> = list.iter().map(|item| item.to_string()).collect();
+    list.join("\n")
+}
+
+macro_rules! cmp_field {
+    ($reason:ident, $tag:expr, $want:ident, $got:ident, $field:tt) => {
+        if $want.$field != $got.$field {
+            $reason.push(FieldMismatch::new(
+                format!("{}.{}", $tag, stringify!($field)),
+                &$want.$field,
+                &$got.$field,
+            ));
+        }
+    };
+}
+
 impl Receipt {
-    pub fn compare(want: &Receipt, got: &Receipt) -> Result<(), String> {
-        let mut reason = <Vec<String>>::new();
-        let mut tag = String::from("");
-        macro_rules! cmp {
-            ($want:tt, $got:tt, $field:tt) => {
-                if $want.$field != $got.$field {
-                    reason.push(format!(
-                        "[{}.{}] not match, want: {:?}, got: {:?}",
-                        tag,
-                        stringify!($field),
-                        $want.$field,
-                        $got.$field
-                    ));
-                }
-            };
+    pub fn diff(want: &Receipt, got: &Receipt) -> Result<(), Vec<FieldMismatch>> {
+        let mut reason = <Vec<FieldMismatch>>::new();
+        let tag = "";
+        cmp_field!(reason, tag, want, got, r#type);
+        cmp_field!(reason, tag, want, got, root);
+        cmp_field!(reason, tag, want, got, status);
+        // cmp_field!(reason, tag, want, got, cumulative_gas_used);
+        cmp_field!(reason, tag, want, got, logs_bloom);
+        cmp_field!(reason, tag, want, got, transaction_hash);
+        cmp_field!(reason, tag, want, got, contract_address);
+        cmp_field!(reason, tag, want, got, gas_used);
+        // cmp_field!(reason, tag, want, got, block_hash);
+        // cmp_field!(reason, tag, want, got, block_number);
+        // cmp_field!(reason, tag, want, got, transaction_index);
+        if want.logs.len() != got.logs.len() {
+            reason.push(FieldMismatch::new(
+                "logs.len".into(),
+                &want.logs.len(),
+                &got.logs.len(),
+            ));
+            for (i, log) in want.logs.iter().enumerate().skip(got.logs.len()) {
+                reason.push(FieldMismatch::missing(format!("logs.{}", i), log));
+            }
+            for (i, log) in got.logs.iter().enumerate().skip(want.logs.len()) {
+                reason.push(FieldMismatch::unexpected(format!("logs.{}", i), log));
+            }
         }
-        cmp!(want, got, r#type);
-        cmp!(want, got, root);
-        cmp!(want, got, status);
-        // cmp!(want, got, cumulative_gas_used);
-        cmp!(want, got, logs_bloom);
-        // cmp!(want, got, logs.len());
-        cmp!(want, got, transaction_hash);
-        cmp!(want, got, contract_address);
-        cmp!(want, got, gas_used);
-        // cmp!(want, got, block_hash);
-        // cmp!(want, got, block_number);
-        // cmp!(want, got, transaction_index);
-        for i in 0..want.logs.len() {
-            tag = format!("logs.{}", i);
-            let want = &want.logs[i];
-            let got = &got.logs[i];
-            cmp!(want, got, address);
-            cmp!(want, got, topics);
-            cmp!(want, got, data);
-            // cmp!(want, got, block_number);
-            cmp!(want, got, transaction_hash);
-            // cmp!(want, got, transaction_index);
-            // cmp!(want, got, block_hash);
-            // cmp!(want, got, log_index);
-            // cmp!(want, got, removed);
+        for (i, (want, got)) in want.logs.iter().zip(&got.logs).enumerate() {
+            let tag = format!("logs.{}", i);
+            cmp_field!(reason, tag, want, got, address);
+            cmp_field!(reason, tag, want, got, topics);
+            cmp_field!(reason, tag, want, got, data);
+            // cmp_field!(reason, tag, want, got, block_number);
+            cmp_field!(reason, tag, want, got, transaction_hash);
+            // cmp_field!(reason, tag, want, got, transaction_index);
+            // cmp_field!(reason, tag, want, got, block_hash);
+            // cmp_field!(reason, tag, want, got, log_index);
+            // cmp_field!(reason, tag, want, got, removed);
         }
         if reason.len() > 0 {
-            return Err(reason.join("\n"));
+            return Err(reason);
         }
         Ok(())
     }
+
+    pub fn compare(want: &Receipt, got: &Receipt) -> Result<(), String> {
+        Self::diff(want, got).map_err(join_mismatches)
+    }
 }
 
 impl Block {
-    pub fn compare(want: &Block, got: &Block) -> Result<(), String> {
-        let mut reason = <Vec<String>>::new();
-        let mut tag: String;
-        macro_rules! cmp {
-            ($want:tt, $got:tt, $field:tt) => {
-                if $want.$field != $got.$field {
-                    reason.push(format!(
-                        "[{}.{}] not match, want: {:?}, got: {:?}",
-                        tag,
-                        stringify!($field),
-                        $want.$field,
-                        $got.$field
-                    ));
-                }
-            };
-        }
+    fn compare_header(want: &Block, got: &Block, reason: &mut Vec<FieldMismatch>) {
+        let tag = "header";
         {
-            tag = "header".into();
             let want = &want.header;
             let got = &got.header;
-            cmp!(want, got, parent_hash);
-            cmp!(want, got, sha3_uncles);
-            cmp!(want, got, miner);
-            cmp!(want, got, state_root);
-            cmp!(want, got, withdrawals_root);
-            cmp!(want, got, transactions_root);
-            cmp!(want, got, receipts_root);
-            cmp!(want, got, logs_bloom);
-            cmp!(want, got, difficulty);
-            cmp!(want, got, number);
-            cmp!(want, got, gas_limit);
-            cmp!(want, got, gas_used);
-            cmp!(want, got, timestamp);
-            // cmp!(want, got, extra_data);
-            cmp!(want, got, mix_hash);
-            cmp!(want, got, nonce);
-            cmp!(want, got, base_fee_per_gas);
-        }
-
-        cmp!(want, got, withdrawals);
+            cmp_field!(reason, tag, want, got, parent_hash);
+            cmp_field!(reason, tag, want, got, sha3_uncles);
+            cmp_field!(reason, tag, want, got, miner);
+            cmp_field!(reason, tag, want, got, state_root);
+            cmp_field!(reason, tag, want, got, withdrawals_root);
+            cmp_field!(reason, tag, want, got, transactions_root);
+            cmp_field!(reason, tag, want, got, receipts_root);
+            cmp_field!(reason, tag, want, got, logs_bloom);
+            cmp_field!(reason, tag, want, got, difficulty);
+            cmp_field!(reason, tag, want, got, number);
+            cmp_field!(reason, tag, want, got, gas_limit);
+            cmp_field!(reason, tag, want, got, gas_used);
+            cmp_field!(reason, tag, want, got, timestamp);
+            // cmp_field!(reason, tag, want, got, extra_data);
+            cmp_field!(reason, tag, want, got, mix_hash);
+            cmp_field!(reason, tag, want, got, nonce);
+            cmp_field!(reason, tag, want, got, base_fee_per_gas);
+        }
+
+        cmp_field!(reason, tag, want, got, withdrawals);
+    }
+
+    fn compare_tx(
+        tag: &str,
+        want: &Transaction,
+        got: &Transaction,
+        reason: &mut Vec<FieldMismatch>,
+    ) {
+        // cmp_field!(reason, tag, want, got, block_hash);
+        cmp_field!(reason, tag, want, got, block_number);
+        // cmp_field!(reason, tag, want, got, from);
+        cmp_field!(reason, tag, want, got, gas);
+        cmp_field!(reason, tag, want, got, gas_price);
+        cmp_field!(reason, tag, want, got, max_fee_per_gas);
+        cmp_field!(reason, tag, want, got, max_priority_fee_per_gas);
+        cmp_field!(reason, tag, want, got, hash);
+        cmp_field!(reason, tag, want, got, input);
+        cmp_field!(reason, tag, want, got, nonce);
+        cmp_field!(reason, tag, want, got, to);
+        // cmp_field!(reason, tag, want, got, transaction_index);
+        cmp_field!(reason, tag, want, got, value);
+        cmp_field!(reason, tag, want, got, r#type);
+        cmp_field!(reason, tag, want, got, access_list);
+        // cmp_field!(reason, tag, want, got, chain_id);
+        cmp_field!(reason, tag, want, got, v);
+        cmp_field!(reason, tag, want, got, r);
+        cmp_field!(reason, tag, want, got, s);
+    }
+
+    fn compare_txs_by_hash(want: &Block, got: &Block, reason: &mut Vec<FieldMismatch>) {
+        for (idx, tx) in want.transactions.iter().enumerate() {
+            match got.transactions.iter().find(|got| got.hash == tx.hash) {
+                Some(got) => {
+                    let tag = format!("tx.{}", idx);
+                    Self::compare_tx(&tag, tx, got, reason);
+                }
+                None => reason.push(FieldMismatch::missing(format!("txs.{}", idx), &tx.hash)),
+            }
+        }
+        for (idx, tx) in got.transactions.iter().enumerate() {
+            if !want.transactions.iter().any(|want| want.hash == tx.hash) {
+                reason.push(FieldMismatch::unexpected(format!("txs.{}", idx), &tx.hash));
+            }
+        }
+    }
+
+    fn is_reordered(want: &Block, got: &Block) -> bool {
+        let shifted = want
+            .transactions
+            .iter()
+            .zip(&got.transactions)
+            .any(|(want, got)| want.hash != got.hash);
+        shifted
+            && want
+                .transactions
+                .iter()
+                .all(|tx| got.transactions.iter().any(|got| got.hash == tx.hash))
+    }
+
+    pub fn diff(want: &Block, got: &Block) -> Result<(), Vec<FieldMismatch>> {
+        let mut reason = <Vec<FieldMismatch>>::new();
+        Self::compare_header(want, got, &mut reason);
 
         if want.transactions.len() != got.transactions.len() {
-            reason.push(format!(
-                "[txs.len] not match, want: {:?}, got: {:?}",
-                want.transactions.len(),
-                got.transactions.len()
+            reason.push(FieldMismatch::new(
+                "txs.len".into(),
+                &want.transactions.len(),
+                &got.transactions.len(),
             ));
             if want.transactions.len() < got.transactions.len() {
                 for (idx, tx) in got.transactions.iter().enumerate() {
                     let search = tx.hash;
                     if !want.transactions.iter().any(|tx| tx.hash == search) {
-                        reason.push(format!("[txs.{}] unexpected {:?}", idx, search));
+                        reason.push(FieldMismatch::unexpected(format!("txs.{}", idx), &search));
                     }
                 }
             }
+        } else if Self::is_reordered(want, got) {
+            // same transactions in a different order, report the order once
+            // instead of a mismatch for every shifted position
+            reason.extend(Self::diff_order(want, got));
+            Self::compare_txs_by_hash(want, got, &mut reason);
         } else {
             for i in 0..want.transactions.len() {
-                tag = format!("tx.{}", i);
-                let want = &want.transactions[i];
-                let got = &got.transactions[i];
-                // cmp!(want, got, block_hash);
-                cmp!(want, got, block_number);
-                // cmp!(want, got, from);
-                cmp!(want, got, gas);
-                cmp!(want, got, gas_price);
-                cmp!(want, got, max_fee_per_gas);
-                cmp!(want, got, max_priority_fee_per_gas);
-                cmp!(want, got, hash);
-                cmp!(want, got, input);
-                cmp!(want, got, nonce);
-                cmp!(want, got, to);
-                // cmp!(want, got, transaction_index);
-                cmp!(want, got, value);
-                cmp!(want, got, r#type);
-                cmp!(want, got, access_list);
-                // cmp!(want, got, chain_id);
-                cmp!(want, got, v);
-                cmp!(want, got, r);
-                cmp!(want, got, s);
+                let tag = format!("tx.{}", i);
+                Self::compare_tx(
+                    &tag,
+                    &want.transactions[i],
+                    &got.transactions[i],
+                    &mut reason,
+                );
             }
         }
 
         if reason.len() > 0 {
-            return Err(reason.join("\n"));
+            return Err(reason);
         }
         Ok(())
     }
+
+    pub fn compare(want: &Block, got: &Block) -> Result<(), String> {
+        Self::diff(want, got).map_err(join_mismatches)
+    }
+
+    // Reports a `txs.order` mismatch when the transactions present in both
+    // blocks appear in a different relative order.
+    pub fn diff_order(want: &Block, got: &Block) -> Option<FieldMismatch> {
+        let want_order: Vec<SH256> = want
+            .transactions
+            .iter()
+            .map(|tx| tx.hash)
+            .filter(|hash| got.transactions.iter().any(|tx| &tx.hash == hash))
+            .collect();
+        let got_order: Vec<SH256> = got
+            .transactions
+            .iter()
+            .map(|tx| tx.hash)
+            .filter(|hash| want.transactions.iter().any(|tx| &tx.hash == hash))
+            .collect();
+        if want_order == got_order {
+            return None;
+        }
+        Some(FieldMismatch::new(
+            "txs.order".into(),
+            &want_order,
+            &got_order,
+        ))
+    }
+
+    // Like `diff`, but transactions are matched by hash, so a block with
+    // the same transactions in a different order is considered equal.
+    // The ordering difference is informational and is not part of the
+    // returned list, use `diff_order` to get it.
+    pub fn diff_unordered(want: &Block, got: &Block) -> Result<(), Vec<FieldMismatch>> {
+        let mut reason = <Vec<FieldMismatch>>::new();
+        Self::compare_header(want, got, &mut reason);
+        Self::compare_txs_by_hash(want, got, &mut reason);
+
+        if reason.len() > 0 {
+            return Err(reason);
+        }
+        Ok(())
+    }
+
+    pub fn compare_unordered(want: &Block, got: &Block) -> Result<(), String> {
+        Self::diff_unordered(want, got).map_err(join_mismatches)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn log(address: &str) -> Log {
+        Log {
+            address: address.into(),
+            topics: Vec::new(),
+            data: HexBytes::new(),
+            block_number: SU64::default(),
+            transaction_hash: SH256::default(),
+            transaction_index: SU64::default(),
+            block_hash: SH256::default(),
+            log_index: SU64::default(),
+            removed: false,
+        }
+    }
+
+    #[test]
+    fn test_receipt_diff_single_field() {
+        let want = Receipt {
+            gas_used: 21000_u64.into(),
+            ..Default::default()
+        };
+        let mut got = want.clone();
+        assert_eq!(Receipt::diff(&want, &got), Ok(()));
+
+        got.gas_used = 21001_u64.into();
+        let mismatches = Receipt::diff(&want, &got).unwrap_err();
+        assert_eq!(mismatches.len(), 1);
+        assert_eq!(mismatches[0].field, ".gas_used");
+        assert_eq!(mismatches[0].want, Some(serde_json::json!("0x5208")));
+        assert_eq!(mismatches[0].got, Some(serde_json::json!("0x5209")));
+    }
+
+    #[test]
+    fn test_receipt_diff_logs_len() {
+        let addr = "0x3535353535353535353535353535353535353535";
+        let want = Receipt {
+            logs: vec![log(addr), log(addr)],
+            ..Default::default()
+        };
+        let got = Receipt {
+            logs: vec![log(addr)],
+            ..Default::default()
+        };
+
+        let mismatches = Receipt::diff(&want, &got).unwrap_err();
+        let fields: Vec<&str> = mismatches.iter().map(|m| m.field.as_str()).collect();
+        assert_eq!(fields, vec!["logs.len", "logs.1"]);
+        assert_eq!(mismatches[1].got, None);
+
+        let mismatches = Receipt::diff(&got, &want).unwrap_err();
+        let fields: Vec<&str> = mismatches.iter().map(|m| m.field.as_str()).collect();
+        assert_eq!(fields, vec!["logs.len", "logs.1"]);
+        assert_eq!(mismatches[1].want, None);
+        assert!(Receipt::compare(&got, &want)
+            .unwrap_err()
+            .contains("[logs.1] unexpected"));
+    }
+
+    fn block(txs: &[&str]) -> Block {
+        let transactions = txs
+            .iter()
+            .map(|hash| Transaction {
+                hash: SH256::from(*hash),
+                ..Default::default()
+            })
+            .collect();
+        Block {
+            header: BlockHeader::default(),
+            transactions,
+            withdrawals: None,
+        }
+    }
+
+    #[test]
+    fn test_diff_unordered() {
+        let want = block(&["0x01", "0x02", "0x03"]);
+        let got = block(&["0x03", "0x01", "0x02"]);
+
+        assert_eq!(Block::diff_unordered(&want, &got), Ok(()));
+        assert_eq!(Block::compare_unordered(&want, &got), Ok(()));
+        let order = Block::diff_order(&want, &got).unwrap();
+        assert_eq!(order.field, "txs.order");
+        let got_order: Vec<SH256> = got.transactions.iter().map(|tx| tx.hash).collect();
+        assert_eq!(order.got, Some(serde_json::to_value(got_order).unwrap()));
+        assert_eq!(Block::diff_order(&want, &want), None);
+
+        // the strict diff reports the order together with the errors
+        let mismatches = Block::diff(&want, &got).unwrap_err();
+        assert_eq!(mismatches, vec![order]);
+
+        // headers are still compared strictly
+        let mut got = got;
+        got.header.gas_used = 1_u64.into();
+        let mismatches = Block::diff_unordered(&want, &got).unwrap_err();
+        let fields: Vec<&str> = mismatches.iter().map(|m| m.field.as_str()).collect();
+        assert_eq!(fields, vec!["header.gas_used"]);
+
+        // a missing transaction doesn't count as reordering
+        let got = block(&["0x01", "0x03"]);
+        assert_eq!(Block::diff_order(&want, &got), None);
+        let mismatches = Block::diff_unordered(&want, &got).unwrap_err();
+        assert_eq!(mismatches.len(), 1);
+        assert_eq!(mismatches[0].field, "txs.1");
+        assert_eq!(mismatches[0].got, None);
+    }
+
+    fn block_json(transactions: serde_json::Value) -> serde_json::Value {
+        serde_json::json!({
+            "baseFeePerGas": "0x7",
+            "difficulty": "0x0",
+            "extraData": "0x",
+            "gasLimit": "0x1c9c380",
+            "gasUsed": "0x5208",
+            "hash": "0x5b0a0d4f3f3a5d6c0c1b6a2d5ad1dc5c4e2f7c5b4a3e2d1c0b9a8f7e6d5c4b3a",
+            "logsBloom": format!("0x{}", "00".repeat(256)),
+            "miner": "0x3535353535353535353535353535353535353535",
+            "mixHash": "0x0000000000000000000000000000000000000000000000000000000000000000",
+            "nonce": "0x0000000000000000",
+            "number": "0x10",
+            "parentHash": "0x1111111111111111111111111111111111111111111111111111111111111111",
+            "receiptsRoot": "0x2222222222222222222222222222222222222222222222222222222222222222",
+            "sha3Uncles": "0x1dcc4de8dec75d7aab85b567b6ccd41ad312451b948a7413f0a142fd40d49347",
+            "size": "0x2a0",
+            "stateRoot": "0x3333333333333333333333333333333333333333333333333333333333333333",
+            "timestamp": "0x6400",
+            "totalDifficulty": "0x0",
+            "transactions": transactions,
+            "transactionsRoot": "0x4444444444444444444444444444444444444444444444444444444444444444",
+            "uncles": [],
+        })
+    }
+
+    #[test]
+    fn test_deserialize_generic_block() {
+        let tx =
+            TransactionInner::from_bytes(&hex::decode(&crate::tx::tests::LEGACY_TX[2..]).unwrap())
+                .unwrap()
+                .to_transaction(None);
+
+        let full = block_json(serde_json::json!([tx]));
+        let block: Block = serde_json::from_value(full).unwrap();
+        assert_eq!(block.header.number, SU64::from(16_u64));
+        assert_eq!(block.header.base_fee_per_gas, SU256::from(7_u64));
+        assert_eq!(block.header.withdrawals_root, None);
+        assert_eq!(block.transactions, vec![tx.clone()]);
+        assert_eq!(block.withdrawals, None);
+
+        let hashes = block_json(serde_json::json!([tx.hash]));
+        let simple: BlockSimple<BlockHeader, Withdrawal> = serde_json::from_value(hashes).unwrap();
+        assert_eq!(simple.header, block.header);
+        assert_eq!(simple.transactions, vec![tx.hash]);
+
+        // the header stays flattened next to the transactions
+        let json = serde_json::to_value(&simple).unwrap();
+        assert_eq!(json["number"], "0x10");
+        assert_eq!(
+            json["transactions"][0],
+            serde_json::to_value(tx.hash).unwrap()
+        );
+    }
+
+    #[test]
+    fn test_validate_indices() {
+        let mut block = block(&["0x01", "0x02", "0x03"]);
+        for (idx, tx) in block.transactions.iter_mut().enumerate() {
+            tx.transaction_index = Some((idx as u64).into());
+        }
+        assert_eq!(block.validate_indices(), Ok(()));
+        assert_eq!(self::block(&[]).validate_indices(), Ok(()));
+
+        let mut gap = block.clone();
+        gap.transactions[2].transaction_index = Some(3_u64.into());
+        assert_eq!(
+            gap.validate_indices(),
+            Err("[tx.2] unexpected transaction index: 3".into())
+        );
+
+        let mut missing = block;
+        missing.transactions.remove(0);
+        assert!(missing
+            .validate_indices()
+            .unwrap_err()
+            .starts_with("[tx.0]"));
+        missing.transactions[0].transaction_index = None;
+        assert_eq!(
+            missing.validate_indices(),
+            Err("[tx.0] missing transaction index".into())
+        );
+    }
+
+    #[test]
+    fn test_range_bloom() {
+        let addrs = [
+            "0x3535353535353535353535353535353535353535",
+            "0x9d8a62f656a8d1615c1294fd71e9cfb3e4855a4f",
+            "0x34b544d335dc19a7cbef592c72e74c9745e603bd",
+        ];
+        let receipts: Vec<Receipt> = addrs
+            .iter()
+            .map(|addr| Receipt {
+                logs: vec![log(addr)],
+                ..Default::default()
+            })
+            .collect();
+
+        let bloom = range_bloom(&receipts);
+        for addr in addrs {
+            assert!(bloom.contains(SH160::from(addr).as_bytes()), "{}", addr);
+        }
+        assert_eq!(bloom, create_bloom(receipts.iter()));
+
+        let mut acc = range_bloom(&receipts[..1]);
+        assert!(!acc.contains(SH160::from(addrs[1]).as_bytes()));
+        for receipt in &receipts[1..] {
+            acc.accrue(receipt);
+        }
+        assert_eq!(acc, bloom);
+        assert_eq!(range_bloom(&[]), Bloom::new());
+    }
+
+    // mainnet genesis, `[header, [], []]`
+    fn genesis() -> (Vec<u8>, BlockHeader) {
+        let raw = format!(
+            "f90219f90214a0{}a01dcc4de8dec75d7aab85b567b6ccd41ad312451b948a7413f0a142fd40d49347\
+             94{}a0d7f8974fb5ac78d9ac099b9ad5018bedc2ce0a72dad1827a1709da30580f0544\
+             a056e81f171bcc55a6ff8345e692c0f86e5b48e01b996cadc001622fb5e363b421\
+             a056e81f171bcc55a6ff8345e692c0f86e5b48e01b996cadc001622fb5e363b421\
+             b90100{}850400000000808213888080\
+             a011bbe8db4e347b4e8c937c1c8370e4b5ed33adb3db69cbdb7a38e1e50b1b82fa\
+             a0{}880000000000000042c0c0",
+            "00".repeat(32),
+            "00".repeat(20),
+            "00".repeat(256),
+            "00".repeat(32),
+        );
+        let header = BlockHeader {
+            sha3_uncles: "0x1dcc4de8dec75d7aab85b567b6ccd41ad312451b948a7413f0a142fd40d49347"
+                .into(),
+            state_root: "0xd7f8974fb5ac78d9ac099b9ad5018bedc2ce0a72dad1827a1709da30580f0544".into(),
+            transactions_root: crate::StateAccount::empty_root(),
+            receipts_root: crate::StateAccount::empty_root(),
+            logs_bloom: Bloom::new().to_hex(),
+            difficulty: SU256::from(0x400000000_u64),
+            gas_limit: 5000_u64.into(),
+            extra_data: hex::decode(
+                "11bbe8db4e347b4e8c937c1c8370e4b5ed33adb3db69cbdb7a38e1e50b1b82fa",
+            )
+            .unwrap()
+            .into(),
+            nonce: BlockNonce::from([0x42_u64]),
+            ..Default::default()
+        };
+        (hex::decode(&raw).unwrap(), header)
+    }
+
+    #[test]
+    fn test_split_block_rlp() {
+        let (data, header) = genesis();
+        let (raw_header, body) = split_block_rlp(&data).unwrap();
+        assert_eq!(raw_header.len(), 535);
+        assert_eq!(body, &[0xc0, 0xc0]);
+        assert_eq!(
+            SH256::from(keccak_hash(raw_header)),
+            "0xd4e56740f876aef8c010b86a40d5f56745a118d0906a34e69aec8c0db1cb8fa3".into()
+        );
+        assert_eq!(header.hash(), SH256::from(keccak_hash(raw_header)));
+
+        assert!(split_block_rlp(&data[..data.len() - 1]).is_err());
+        assert!(split_block_rlp(&[0x80]).is_err());
+        assert!(split_block_rlp(&[0xc1, 0x80]).is_err());
+    }
+
+    #[test]
+    fn test_deserialize_block_selector() {
+        let parse = |val: &str| serde_json::from_value::<BlockSelector>(serde_json::json!(val));
+        let hash = "0x88e96d4537bea4d9c05d12549907b32561d3bf31f45aae734cdc119f13406cb6";
+        assert_eq!(parse("latest").unwrap(), BlockSelector::Latest);
+        assert_eq!(parse("pending").unwrap(), BlockSelector::Pending);
+        assert_eq!(parse("safe").unwrap(), BlockSelector::Safe);
+        assert_eq!(parse("finalized").unwrap(), BlockSelector::Finalized);
+        assert_eq!(parse(hash).unwrap(), BlockSelector::Hash(hash.into()));
+        assert_eq!(parse("0x10").unwrap(), BlockSelector::Number(16_u64.into()));
+        assert_eq!(parse("0x1").unwrap(), BlockSelector::Number(1_u64.into()));
+        assert_eq!(parse("16").unwrap(), BlockSelector::Number(16_u64.into()));
+
+        let params: (SH160, BlockSelector) = serde_json::from_value(serde_json::json!([
+            "0x3535353535353535353535353535353535353535",
+            "0x10"
+        ]))
+        .unwrap();
+        assert_eq!(params.1, 16_u64.into());
+
+        for val in ["latests", "0xzz", "ten", "0x10000000000000000", &hash[..65]] {
+            assert!(parse(val).is_err(), "{:?}", val);
+            assert!(BlockSelector::parse(val).is_err(), "{:?}", val);
+        }
+        assert!(serde_json::from_value::<BlockSelector>(serde_json::json!(16)).is_err());
+    }
+
+    #[test]
+    fn test_block_selector_tags() {
+        for (tag, selector) in [
+            ("latest", BlockSelector::Latest),
+            ("earliest", BlockSelector::Earliest),
+            ("pending", BlockSelector::Pending),
+            ("safe", BlockSelector::Safe),
+            ("finalized", BlockSelector::Finalized),
+        ] {
+            assert_eq!(serde_json::to_value(selector).unwrap(), tag);
+            assert_eq!(BlockSelector::parse(tag), Ok(selector));
+            assert_eq!(BlockSelector::from(tag.to_string()), selector);
+            let got: BlockSelector = serde_json::from_value(serde_json::json!(tag)).unwrap();
+            assert_eq!(got, selector);
+            assert_eq!(selector.as_number(), None);
+        }
+        let number = BlockSelector::Number(16_u64.into());
+        assert_eq!(serde_json::to_value(number).unwrap(), "0x10");
+        assert_eq!(number.as_number(), Some(16_u64.into()));
+
+        assert!(BlockSelector::parse("unsafe").is_err());
+        assert!(serde_json::from_value::<BlockSelector>(serde_json::json!("Finalized")).is_err());
+    }
+
+    #[test]
+    fn test_compare_swapped_txs() {
+        let want = block(&["0x01", "0x02"]);
+        let mut got = block(&["0x02", "0x01"]);
+
+        let mismatches = Block::diff(&want, &got).unwrap_err();
+        let fields: Vec<&str> = mismatches.iter().map(|m| m.field.as_str()).collect();
+        assert_eq!(fields, vec!["txs.order"]);
+        assert!(Block::compare(&want, &got)
+            .unwrap_err()
+            .contains("txs.order"));
+        assert_eq!(Block::compare_unordered(&want, &got), Ok(()));
+
+        // transactions are matched by hash, not by position
+        got.transactions[1].nonce = 1_u64.into();
+        let mismatches = Block::diff(&want, &got).unwrap_err();
+        let fields: Vec<&str> = mismatches.iter().map(|m| m.field.as_str()).collect();
+        assert_eq!(fields, vec!["txs.order", "tx.0.nonce"]);
+    }
+
+    #[test]
+    fn test_estimate_block_size() {
+        use crate::tx::tests::{ACCESS_LIST_TX, DYNAMIC_FEE_TX, LEGACY_TX};
+        let (genesis, header) = genesis();
+        assert_eq!(estimate_block_size(&header, &[], None), genesis.len());
+
+        // the genesis header with a legacy, an access list and a dynamic fee
+        // tx, no uncles and 3 withdrawals
+        let raw_header = split_block_rlp(&genesis).unwrap().0;
+        let raw = format!(
+            "f903f4{}f90188{}b8a8{}b86e{}c0f84f\
+             d8808094353535353535353535353535353535353535353580\
+             da01079435353535353535353535353535353535353535358203e8\
+             da020e9435353535353535353535353535353535353535358207d0",
+            hex::encode(raw_header),
+            &LEGACY_TX[2..],
+            &ACCESS_LIST_TX[2..],
+            &DYNAMIC_FEE_TX[2..],
+        );
+        let data = hex::decode(&raw).unwrap();
+        assert_eq!(rlp::Rlp::new(&data).item_count(), Ok(4));
+
+        let txs: Vec<TransactionInner> = [LEGACY_TX, ACCESS_LIST_TX, DYNAMIC_FEE_TX]
+            .iter()
+            .map(|raw| TransactionInner::from_bytes(&hex::decode(&raw[2..]).unwrap()).unwrap())
+            .collect();
+        let withdrawals: Vec<Withdrawal> = (0..3_u64)
+            .map(|i| Withdrawal {
+                index: i.into(),
+                validator_index: (i * 7).into(),
+                address: "0x3535353535353535353535353535353535353535".into(),
+                amount: (i * 1000).into(),
+            })
+            .collect();
+        assert_eq!(
+            estimate_block_size(&header, &txs, Some(&withdrawals)),
+            data.len()
+        );
+    }
+
+    #[test]
+    fn test_header_bloom() {
+        let mut bloom = Bloom::new();
+        bloom.add_logs(&[log("0x3535353535353535353535353535353535353535")]);
+
+        let mut header = BlockHeader::default();
+        header.set_bloom(&bloom);
+        assert_eq!(header.logs_bloom.len(), 256);
+        assert_eq!(header.bloom(), Ok(bloom));
+
+        header.logs_bloom = HexBytes::from(&[0_u8; 255][..]);
+        assert_eq!(
+            header.bloom(),
+            Err("invalid logs bloom length, want: 256, got: 255".into())
+        );
+        header.logs_bloom = HexBytes::new();
+        assert!(header.bloom().is_err());
+    }
+
+    #[test]
+    fn test_bloom_serde_and_rlp() {
+        let mut bloom = Bloom::new();
+        bloom.add_logs(&[log("0x3535353535353535353535353535353535353535")]);
+
+        let json = serde_json::to_value(&bloom).unwrap();
+        let hex = json.as_str().unwrap();
+        assert_eq!(hex.len(), 2 + 512);
+        assert_eq!(hex, bloom.to_hex().to_string());
+        assert_eq!(serde_json::from_value::<Bloom>(json).unwrap(), bloom);
+        assert_eq!(
+            serde_json::to_value(Bloom::new()).unwrap(),
+            format!("0x{}", "00".repeat(256))
+        );
+        assert!(serde_json::from_value::<Bloom>(serde_json::json!("0x00")).is_err());
+
+        let encoded = rlp::encode(&bloom);
+        assert_eq!(encoded.len(), 3 + 256);
+        assert_eq!(&encoded[..3], &[0xb9, 0x01, 0x00]);
+        assert_eq!(rlp::decode::<Bloom>(&encoded), Ok(bloom));
+        assert_eq!(
+            rlp::decode::<Bloom>(&rlp::encode(&vec![0_u8; 255])),
+            Err(rlp::DecoderError::RlpInvalidLength)
+        );
+    }
+
+    #[test]
+    fn test_bloom_bits_set() {
+        let empty = Bloom::new();
+        assert_eq!(empty.bits_set(), 0);
+        assert_eq!(empty.saturation(), 0.0);
+
+        // every added item sets at most 3 bits
+        let mut bloom = Bloom::new();
+        bloom.add_logs(&[log("0x3535353535353535353535353535353535353535")]);
+        assert!(bloom.bits_set() > 0 && bloom.bits_set() <= 3);
+        assert_eq!(bloom.saturation(), bloom.bits_set() as f64 / 2048.0);
+
+        let mut full = Bloom::new();
+        full.0 = [0xff; 256];
+        assert_eq!(full.bits_set(), 2048);
+        assert_eq!(full.saturation(), 1.0);
+    }
+
+    #[test]
+    fn test_tx_order_hash() {
+        let want = block(&["0x01", "0x02", "0x03"]);
+        let got = block(&["0x01", "0x03", "0x02"]);
+        assert_ne!(want.tx_order_hash(), got.tx_order_hash());
+        assert_eq!(
+            want.tx_order_hash(),
+            block(&["0x01", "0x02", "0x03"]).tx_order_hash()
+        );
+
+        let mut data = Vec::new();
+        for tx in &want.transactions {
+            data.extend_from_slice(tx.hash.as_bytes());
+        }
+        assert_eq!(want.tx_order_hash(), SH256::from(keccak_hash(&data)));
+        // keccak of nothing
+        assert_eq!(
+            block(&[]).tx_order_hash(),
+            "0xc5d2460186f7233c927e7db2dcc703c0e500b653ca82273b7bfad8045d85a470".into()
+        );
+    }
+
+    #[test]
+    fn test_block_selector_from_number() {
+        let want = BlockSelector::Number(16_u64.into());
+        assert_eq!(BlockSelector::from(16_u64), want);
+        assert_eq!(BlockSelector::from(SU64::from(16_u64)), want);
+        assert_eq!(BlockSelector::from(SU256::from(16_u64)), want);
+        assert_eq!(BlockSelector::from("0x10".to_string()), want);
+        for selector in [
+            BlockSelector::from(16_u64),
+            BlockSelector::from(SU64::from(16_u64)),
+            BlockSelector::from(SU256::from(16_u64)),
+        ] {
+            assert_eq!(selector.as_number(), Some(16_u64.into()));
+        }
+
+        let hash = SH256::from([1_u8; 32]);
+        assert_eq!(BlockSelector::from(hash), BlockSelector::Hash(hash));
+        assert_eq!(BlockSelector::from(hash).as_number(), None);
+        assert_eq!(BlockSelector::Latest.as_number(), None);
+    }
+
+    #[test]
+    fn test_raw_transactions() {
+        use crate::tx::tests::{ACCESS_LIST_TX, DYNAMIC_FEE_TX, LEGACY_TX};
+        let raws = [LEGACY_TX, ACCESS_LIST_TX, DYNAMIC_FEE_TX];
+        let mut block = block(&[]);
+        block.transactions = raws
+            .iter()
+            .map(|raw| {
+                TransactionInner::from_bytes(&hex::decode(&raw[2..]).unwrap())
+                    .unwrap()
+                    .to_transaction(None)
+            })
+            .collect();
+        let got = block.raw_transactions().unwrap();
+        let want: Vec<Vec<u8>> = raws
+            .iter()
+            .map(|raw| hex::decode(&raw[2..]).unwrap())
+            .collect();
+        assert_eq!(got, want);
+
+        block.transactions[1].access_list = None;
+        let err = block.raw_transactions().unwrap_err();
+        assert!(
+            err.starts_with("[tx.1] missing fields for tx type 1"),
+            "{}",
+            err
+        );
+    }
+
+    #[test]
+    fn test_set_logs_bloom_from_receipts() {
+        let receipts: Vec<Receipt> = [
+            "0x3535353535353535353535353535353535353535",
+            "0x9d8a62f656a8d1615c1294fd71e9cfb3e4855a4f",
+        ]
+        .iter()
+        .map(|addr| Receipt {
+            logs: vec![log(addr)],
+            ..Default::default()
+        })
+        .collect();
+
+        let mut header = BlockHeader::default();
+        header.set_logs_bloom_from_receipts(&receipts);
+        assert_eq!(header.bloom(), Ok(create_bloom(receipts.iter())));
+        assert_ne!(header.bloom(), Ok(Bloom::new()));
+
+        header.set_logs_bloom_from_receipts(&[]);
+        assert_eq!(header.logs_bloom, Bloom::new().to_hex());
+    }
+
+    #[test]
+    fn test_build_index() {
+        let block = block(&["0x01", "0x02", "0x03"]);
+        let index = block.build_index();
+        assert_eq!(index.len(), 3);
+        for (idx, tx) in block.transactions.iter().enumerate() {
+            assert_eq!(index.get(&tx.hash), Some(&idx));
+        }
+        assert_eq!(index.get(&SH256::from("0x04")), None);
+        assert!(self::block(&[]).build_index().is_empty());
+    }
+
+    #[test]
+    fn test_block_effective_gas_price() {
+        use crate::tx::tests::{DYNAMIC_FEE_TX, LEGACY_TX};
+        let gwei = |n: u64| SU256::from(n * 1_000_000_000);
+        let mut block = block(&[]);
+        // tip of 2 gwei and max fee of 100 gwei, then a 20 gwei legacy tx
+        block.transactions = [DYNAMIC_FEE_TX, LEGACY_TX]
+            .iter()
+            .map(|raw| {
+                TransactionInner::from_bytes(&hex::decode(&raw[2..]).unwrap())
+                    .unwrap()
+                    .to_transaction(None)
+            })
+            .collect();
+
+        block.header.base_fee_per_gas = gwei(30);
+        assert_eq!(block.effective_gas_price(0), Some(gwei(32)));
+        assert_eq!(block.effective_gas_price(1), Some(gwei(20)));
+        assert_eq!(block.effective_gas_price(2), None);
+
+        block.header.base_fee_per_gas = gwei(99);
+        assert_eq!(block.effective_gas_price(0), Some(gwei(100)));
+
+        // base fee + tip overflows, the fee cap applies
+        block.transactions[0].max_priority_fee_per_gas = Some(crate::U256::max_value().into());
+        assert_eq!(block.effective_gas_price(0), Some(gwei(100)));
+    }
 }
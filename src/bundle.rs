@@ -1,10 +1,11 @@
 use std::prelude::v1::*;
 
-use crate::{PoolTx, Signer, SH160, SH256, SU64};
+use crate::{PoolTx, Signer, TransactionInner, SH160, SH256, SU64};
 use crypto::keccak_hash;
 use hex::HexBytes;
 use rlp_derive::{RlpDecodable, RlpEncodable};
 use serde::{Deserialize, Serialize};
+use serde_json::Value;
 
 #[derive(Debug, Clone)]
 pub struct Bundle {
@@ -19,6 +20,12 @@ pub struct Bundle {
 
 impl Bundle {
     pub fn hash(&self) -> SH256 {
+        self.flashbots_hash()
+    }
+
+    // Flashbots bundle hash: keccak256(tx_hash_0 ++ tx_hash_1 ++ ... ++ tx_hash_n),
+    // where each tx_hash is the hash of the signed transaction, in bundle order.
+    pub fn flashbots_hash(&self) -> SH256 {
         let mut hash_bytes = Vec::with_capacity(self.txs.len() * 32);
         for tx in &self.txs {
             hash_bytes.extend_from_slice(tx.hash.as_bytes());
@@ -26,6 +33,49 @@ impl Bundle {
         keccak_hash(&hash_bytes).into()
     }
 
+    pub fn tx_hashes(&self) -> Vec<SH256> {
+        self.txs.iter().map(|tx| tx.hash).collect()
+    }
+
+    pub fn reverting_tx_hashes(&self) -> Vec<SH256> {
+        self.txs
+            .iter()
+            .filter(|tx| tx.allow_revert)
+            .map(|tx| tx.hash)
+            .collect()
+    }
+
+    pub fn set_allow_revert(&mut self, hash: &SH256, allow: bool) -> bool {
+        match self.txs.iter_mut().find(|tx| &tx.hash == hash) {
+            Some(tx) => {
+                tx.allow_revert = allow;
+                true
+            }
+            None => false,
+        }
+    }
+
+    pub fn total_gas_limit(&self) -> u64 {
+        self.txs.iter().map(|tx| tx.gas).sum()
+    }
+
+    pub fn is_valid_for_block(&self, block: u64, timestamp: u64) -> bool {
+        if self.block_number.as_u64() != block {
+            return false;
+        }
+        if let Some(min_timestamp) = self.min_timestamp {
+            if timestamp < min_timestamp {
+                return false;
+            }
+        }
+        if let Some(max_timestamp) = self.max_timestamp {
+            if timestamp > max_timestamp {
+                return false;
+            }
+        }
+        true
+    }
+
     pub fn to_rlp(&self) -> BundleRlp {
         let txs = self.txs.iter().map(|n| n.to_bytes()).collect();
         BundleRlp {
@@ -34,6 +84,8 @@ impl Bundle {
             uuid: self.uuid.clone(),
             refund_percent: self.refund_percent,
             refund_recipient: self.refund_recipient,
+            min_timestamp: self.min_timestamp,
+            max_timestamp: self.max_timestamp,
         }
     }
 
@@ -43,7 +95,12 @@ impl Bundle {
     }
 
     pub fn from_bytes(signer: &Signer, data: &[u8]) -> Result<Self, rlp::DecoderError> {
-        let val: BundleRlp = rlp::decode(data)?;
+        // the v1 decoder ignores trailing items, so pick the layout by length
+        let rlp = rlp::Rlp::new(data);
+        let val: BundleRlp = match rlp.item_count()? {
+            3 => rlp.as_val::<BundleRlpV1>()?.into(),
+            _ => rlp.as_val()?,
+        };
         Self::from_rlp(signer, val)
     }
 
@@ -56,23 +113,158 @@ impl Bundle {
             txs,
             block_number: val.block_number,
             uuid: val.uuid,
-            min_timestamp: None,
-            max_timestamp: None,
+            min_timestamp: val.min_timestamp,
+            max_timestamp: val.max_timestamp,
             refund_percent: val.refund_percent,
             refund_recipient: val.refund_recipient,
         })
     }
+
+    #[deprecated(note = "use `to_send_bundle_json`")]
+    pub fn to_flashbots_json(&self) -> Value {
+        self.to_send_bundle_json()
+    }
+
+    pub fn to_send_bundle_json(&self) -> Value {
+        let params = SendBundleParams {
+            txs: self.txs.iter().map(|tx| tx.tx.to_bytes().into()).collect(),
+            block_number: self.block_number,
+            min_timestamp: self.min_timestamp,
+            max_timestamp: self.max_timestamp,
+            reverting_tx_hashes: self.reverting_tx_hashes(),
+        };
+        serde_json::to_value(&params).unwrap()
+    }
+
+    #[deprecated(note = "use `from_send_bundle_json`")]
+    pub fn from_flashbots_json(signer: &Signer, value: &Value) -> Result<Self, String> {
+        Self::from_send_bundle_json(signer, value)
+    }
+
+    pub fn from_send_bundle_json(signer: &Signer, value: &Value) -> Result<Self, String> {
+        let params: SendBundleParams = serde_json::from_value(value.clone())
+            .map_err(|err| format!("invalid bundle: {:?}", err))?;
+        let mut txs = Vec::with_capacity(params.txs.len());
+        for (idx, raw) in params.txs.iter().enumerate() {
+            let inner = TransactionInner::from_bytes(raw)
+                .map_err(|err| format!("invalid tx[{}]: {:?}", idx, err))?;
+            let mut tx = PoolTx::try_with_tx(signer, inner)
+                .map_err(|err| format!("invalid tx[{}]: {:?}", idx, err))?;
+            tx.allow_revert = params.reverting_tx_hashes.contains(&tx.hash);
+            txs.push(tx);
+        }
+        Ok(Bundle {
+            txs,
+            block_number: params.block_number,
+            min_timestamp: params.min_timestamp,
+            max_timestamp: params.max_timestamp,
+            uuid: String::new(),
+            refund_percent: 0,
+            refund_recipient: SH160::default(),
+        })
+    }
 }
 
+#[derive(Clone, Debug, Deserialize, Serialize, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub struct SendBundleParams {
+    pub txs: Vec<HexBytes>,
+    pub block_number: SU64,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub min_timestamp: Option<u64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_timestamp: Option<u64>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub reverting_tx_hashes: Vec<SH256>,
+}
+
+// The layout written by `Bundle::to_bytes`.
+pub type BundleRlp = BundleRlpV2;
+
+// Legacy layout, written before the refund fields were introduced.
 #[derive(Clone, Debug, Deserialize, Serialize, RlpEncodable, RlpDecodable, PartialEq, Eq)]
-pub struct BundleRlp {
+pub struct BundleRlpV1 {
+    pub txs: Vec<HexBytes>,
+    pub block_number: SU64,
+    pub uuid: String,
+}
+
+// Each timestamp is encoded as a list, `[]` when unset and `[ts]` otherwise,
+// so that `Some(0)` round-trips. Bundles written before the timestamps were
+// added carry only the first 5 fields and still decode.
+#[derive(Clone, Debug, Deserialize, Serialize, PartialEq, Eq)]
+pub struct BundleRlpV2 {
     pub txs: Vec<HexBytes>,
     pub block_number: SU64,
     pub uuid: String,
     pub refund_percent: u64,
     pub refund_recipient: SH160,
+    pub min_timestamp: Option<u64>,
+    pub max_timestamp: Option<u64>,
 }
 
+impl rlp::Encodable for BundleRlpV2 {
+    fn rlp_append(&self, s: &mut rlp::RlpStream) {
+        s.begin_list(7);
+        s.append_list(&self.txs);
+        s.append(&self.block_number);
+        s.append(&self.uuid);
+        s.append(&self.refund_percent);
+        s.append(&self.refund_recipient);
+        for ts in [&self.min_timestamp, &self.max_timestamp] {
+            let ts: Vec<u64> = ts.iter().cloned().collect();
+            s.append_list(&ts);
+        }
+    }
+}
+
+impl rlp::Decodable for BundleRlpV2 {
+    fn decode(rlp: &rlp::Rlp) -> Result<Self, rlp::DecoderError> {
+        let item_count = rlp.item_count()?;
+        if item_count != 5 && item_count != 7 {
+            return Err(rlp::DecoderError::RlpIncorrectListLen);
+        }
+        let mut val = BundleRlpV2 {
+            txs: rlp.list_at(0)?,
+            block_number: rlp.val_at(1)?,
+            uuid: rlp.val_at(2)?,
+            refund_percent: rlp.val_at(3)?,
+            refund_recipient: rlp.val_at(4)?,
+            min_timestamp: None,
+            max_timestamp: None,
+        };
+        if item_count == 7 {
+            val.min_timestamp = optional_at(rlp, 5)?;
+            val.max_timestamp = optional_at(rlp, 6)?;
+        }
+        Ok(val)
+    }
+}
+
+impl From<BundleRlpV1> for BundleRlpV2 {
+    fn from(val: BundleRlpV1) -> Self {
+        Self {
+            txs: val.txs,
+            block_number: val.block_number,
+            uuid: val.uuid,
+            refund_percent: 0,
+            refund_recipient: SH160::default(),
+            min_timestamp: None,
+            max_timestamp: None,
+        }
+    }
+}
+
+fn optional_at(rlp: &rlp::Rlp, idx: usize) -> Result<Option<u64>, rlp::DecoderError> {
+    let list: Vec<u64> = rlp.list_at(idx)?;
+    match list.as_slice() {
+        [] => Ok(None),
+        [ts] => Ok(Some(*ts)),
+        _ => Err(rlp::DecoderError::RlpIncorrectListLen),
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
 pub enum PoolItemType {
     Tx,
     Bundle,
@@ -95,4 +287,326 @@ impl PoolItem {
             _ => return Err(rlp::DecoderError::Custom("unknown tx prefix")),
         })
     }
+
+    pub fn item_type(&self) -> PoolItemType {
+        match self {
+            PoolItem::Tx(_) => PoolItemType::Tx,
+            PoolItem::Bundle(_) => PoolItemType::Bundle,
+        }
+    }
+
+    pub fn to_bytes(&self) -> HexBytes {
+        let (prefix, data) = match self {
+            PoolItem::Tx(tx) => (1_u8, tx.to_bytes()),
+            PoolItem::Bundle(bundle) => (2_u8, bundle.to_bytes()),
+        };
+        let mut out = Vec::with_capacity(data.len() + 1);
+        out.push(prefix);
+        out.extend_from_slice(&data);
+        out.into()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tx::tests::{decode_tx, signer, CREATE_TX, DYNAMIC_FEE_TX, LEGACY_TX, SENDER};
+    use crate::SU256;
+
+    fn bundle() -> Bundle {
+        let signer = signer();
+        let mut txs: Vec<_> = [LEGACY_TX, DYNAMIC_FEE_TX]
+            .iter()
+            .map(|raw| PoolTx::with_tx(&signer, decode_tx(raw)))
+            .collect();
+        txs[0].allow_revert = false;
+        Bundle {
+            txs,
+            block_number: 100.into(),
+            min_timestamp: Some(10),
+            max_timestamp: None,
+            uuid: String::new(),
+            refund_percent: 0,
+            refund_recipient: SH160::default(),
+        }
+    }
+
+    #[test]
+    fn test_send_bundle_json_roundtrip() {
+        let bundle = bundle();
+        let json = bundle.to_send_bundle_json();
+        #[allow(deprecated)]
+        let alias = bundle.to_flashbots_json();
+        assert_eq!(alias, json);
+        assert_eq!(json["txs"][0], LEGACY_TX);
+        assert_eq!(json["blockNumber"], "0x64");
+        assert_eq!(json["minTimestamp"], 10);
+        assert!(json.get("maxTimestamp").is_none());
+        assert_eq!(
+            json["revertingTxHashes"][0],
+            format!("{:?}", bundle.txs[1].hash)
+        );
+
+        let got = Bundle::from_send_bundle_json(&signer(), &json).unwrap();
+        assert_eq!(got.tx_hashes(), bundle.tx_hashes());
+        assert_eq!(got.reverting_tx_hashes(), bundle.reverting_tx_hashes());
+        assert_eq!(got.txs[1].caller, SENDER.into());
+        #[allow(deprecated)]
+        let got = Bundle::from_flashbots_json(&signer(), &json).unwrap();
+        assert_eq!(got.hash(), bundle.hash());
+    }
+
+    #[test]
+    fn test_send_bundle_json_untrusted_tx() {
+        let json = bundle().to_send_bundle_json();
+        // chain id mismatch for the typed tx
+        let other_chain = Signer::new(SU256::from(5_u64));
+        assert!(Bundle::from_send_bundle_json(&other_chain, &json).is_err());
+
+        let mut tampered = json.clone();
+        let mut raw = hex::decode(&LEGACY_TX[2..]).unwrap();
+        let n = raw.len();
+        raw[n - 1] ^= 0xff;
+        raw[n - 33] = 0xff; // s above the curve order
+        tampered["txs"][0] = format!("0x{}", hex::encode(&raw)).into();
+        assert!(Bundle::from_send_bundle_json(&signer(), &tampered).is_err());
+
+        assert!(Bundle::from_send_bundle_json(&signer(), &serde_json::json!({})).is_err());
+    }
+
+    #[test]
+    fn test_flashbots_hash() {
+        let mut bundle = bundle();
+        assert_eq!(
+            bundle.tx_hashes(),
+            vec![
+                "0x33469b22e9f636356c4160a87eb19df52b7412e8eac32a4a55ffe88ea8350788".into(),
+                "0x0f0ac1eed89575d4705e944343b49434abbc9154b6fae42e15e50148a1da9490".into(),
+            ]
+        );
+        // keccak256(tx_hash_0 ++ tx_hash_1)
+        let want: SH256 =
+            "0xf6b0d002488f94eb8eccf72c73964e477be97e8d65bff49c3c8e3294f6ec1f87".into();
+        assert_eq!(bundle.flashbots_hash(), want);
+        assert_eq!(bundle.hash(), want);
+
+        // reverting txs are part of the preimage like any other tx
+        bundle.txs[0].allow_revert = true;
+        assert_eq!(bundle.flashbots_hash(), want);
+
+        bundle.txs.reverse();
+        assert_eq!(
+            bundle.flashbots_hash(),
+            "0x10d28a2be3252b1e2116979744de7e828df5319a78b09e372660d44cc5eeed17".into()
+        );
+    }
+
+    #[test]
+    fn test_send_bundle_json_sample() {
+        let payload = serde_json::json!({
+            "txs": [LEGACY_TX, DYNAMIC_FEE_TX],
+            "blockNumber": "0xb63dcd",
+            "minTimestamp": 0,
+            "maxTimestamp": 1615920932,
+            "revertingTxHashes": [
+                "0x0f0ac1eed89575d4705e944343b49434abbc9154b6fae42e15e50148a1da9490"
+            ]
+        });
+        let bundle = Bundle::from_send_bundle_json(&signer(), &payload).unwrap();
+        assert_eq!(bundle.block_number, SU64::from(0xb63dcd_u64));
+        assert_eq!(bundle.min_timestamp, Some(0));
+        assert_eq!(bundle.max_timestamp, Some(1615920932));
+        assert_eq!(bundle.txs.len(), 2);
+        assert!(!bundle.txs[0].allow_revert);
+        assert!(bundle.txs[1].allow_revert);
+        assert_eq!(bundle.to_send_bundle_json(), payload);
+    }
+
+    #[test]
+    fn test_total_gas_limit() {
+        let mut bundle = bundle();
+        bundle
+            .txs
+            .push(PoolTx::with_tx(&signer(), decode_tx(CREATE_TX)));
+        for (tx, gas) in bundle.txs.iter_mut().zip([21000, 30000, 100000]) {
+            tx.gas = gas;
+        }
+        assert_eq!(bundle.total_gas_limit(), 151000);
+        assert_eq!(bundle.tx_hashes().len(), 3);
+    }
+
+    #[test]
+    fn test_is_valid_for_block() {
+        let mut bundle = bundle();
+        bundle.max_timestamp = Some(20);
+        assert!(bundle.is_valid_for_block(100, 10));
+        assert!(bundle.is_valid_for_block(100, 20));
+        assert!(!bundle.is_valid_for_block(100, 9));
+        assert!(!bundle.is_valid_for_block(100, 21));
+        assert!(!bundle.is_valid_for_block(101, 15));
+
+        bundle.min_timestamp = None;
+        bundle.max_timestamp = None;
+        assert!(bundle.is_valid_for_block(100, 0));
+        assert!(bundle.is_valid_for_block(100, u64::MAX));
+    }
+
+    #[test]
+    fn test_bundle_rlp_versions() {
+        let mut bundle = bundle();
+        bundle.uuid = "a5a6a7".into();
+        bundle.refund_percent = 90;
+        bundle.refund_recipient = SENDER.into();
+        bundle.max_timestamp = Some(20);
+
+        let got = Bundle::from_bytes(&signer(), &bundle.to_bytes()).unwrap();
+        assert_eq!(got.to_rlp(), bundle.to_rlp());
+        assert_eq!(got.min_timestamp, Some(10));
+        assert_eq!(got.max_timestamp, Some(20));
+        assert_eq!(got.reverting_tx_hashes(), bundle.reverting_tx_hashes());
+
+        // an explicit 0 is kept apart from an unset timestamp
+        let mut zero = bundle.clone();
+        zero.min_timestamp = Some(0);
+        zero.max_timestamp = None;
+        let got = Bundle::from_bytes(&signer(), &zero.to_bytes()).unwrap();
+        assert_eq!((got.min_timestamp, got.max_timestamp), (Some(0), None));
+        let mut s = rlp::RlpStream::new_list(7);
+        let rlp = zero.to_rlp();
+        s.append_list(&rlp.txs);
+        s.append(&rlp.block_number);
+        s.append(&rlp.uuid);
+        s.append(&rlp.refund_percent);
+        s.append(&rlp.refund_recipient);
+        s.append_list(&[1_u64, 2]);
+        s.begin_list(0);
+        assert_eq!(
+            Bundle::from_bytes(&signer(), &s.out()).unwrap_err(),
+            rlp::DecoderError::RlpIncorrectListLen
+        );
+
+        // written before the refund fields existed
+        let rlp = bundle.to_rlp();
+        let v1 = BundleRlpV1 {
+            txs: rlp.txs.clone(),
+            block_number: rlp.block_number,
+            uuid: rlp.uuid.clone(),
+        };
+        let got = Bundle::from_bytes(&signer(), &rlp::encode(&v1)).unwrap();
+        assert_eq!(got.tx_hashes(), bundle.tx_hashes());
+        assert_eq!(got.uuid, bundle.uuid);
+        assert_eq!(got.refund_percent, 0);
+        assert_eq!(got.refund_recipient, SH160::default());
+        assert_eq!((got.min_timestamp, got.max_timestamp), (None, None));
+
+        // written before the timestamps existed
+        let mut s = rlp::RlpStream::new_list(5);
+        s.append_list(&rlp.txs);
+        s.append(&rlp.block_number);
+        s.append(&rlp.uuid);
+        s.append(&rlp.refund_percent);
+        s.append(&rlp.refund_recipient);
+        let got = Bundle::from_bytes(&signer(), &s.out()).unwrap();
+        assert_eq!(got.refund_percent, 90);
+        assert_eq!(got.refund_recipient, SENDER.into());
+        assert_eq!((got.min_timestamp, got.max_timestamp), (None, None));
+
+        assert!(Bundle::from_bytes(&signer(), &rlp::encode(&rlp.uuid)).is_err());
+    }
+
+    #[test]
+    fn test_pool_item_roundtrip() {
+        let tx = PoolTx::with_tx(&signer(), decode_tx(LEGACY_TX));
+        let item = PoolItem::Tx(tx.clone());
+        let data = item.to_bytes();
+        assert_eq!(data[0], 1);
+        match PoolItem::from_bytes(&signer(), &data).unwrap() {
+            PoolItem::Tx(got) => {
+                assert_eq!(got.hash, tx.hash);
+                assert_eq!(got.caller, SENDER.into());
+            }
+            PoolItem::Bundle(_) => unreachable!(),
+        }
+
+        let bundle = bundle();
+        let item = PoolItem::Bundle(bundle.clone());
+        assert_eq!(item.item_type(), PoolItemType::Bundle);
+        let data = item.to_bytes();
+        assert_eq!(data[0], 2);
+        let got = PoolItem::from_bytes(&signer(), &data).unwrap();
+        assert_eq!(got.item_type(), PoolItemType::Bundle);
+        match got {
+            PoolItem::Bundle(got) => assert_eq!(got.to_rlp(), bundle.to_rlp()),
+            PoolItem::Tx(_) => unreachable!(),
+        }
+
+        assert_eq!(
+            PoolItem::from_bytes(&signer(), &[]).unwrap_err(),
+            rlp::DecoderError::RlpIsTooShort
+        );
+        let mut data = data.to_vec();
+        data[0] = 3;
+        assert_eq!(
+            PoolItem::from_bytes(&signer(), &data).unwrap_err(),
+            rlp::DecoderError::Custom("unknown tx prefix")
+        );
+    }
+
+    #[test]
+    fn test_flashbots_hash_preimage() {
+        let mut bundle = bundle();
+        let mut preimage = Vec::new();
+        for tx in &bundle.txs {
+            preimage.extend_from_slice(tx.hash.as_bytes());
+        }
+        assert_eq!(preimage.len(), 64);
+        assert_eq!(bundle.flashbots_hash(), keccak_hash(&preimage).into());
+
+        // no transactions hash the empty preimage
+        bundle.txs.clear();
+        assert_eq!(
+            bundle.flashbots_hash(),
+            "0xc5d2460186f7233c927e7db2dcc703c0e500b653ca82273b7bfad8045d85a470".into()
+        );
+    }
+
+    #[test]
+    fn test_set_allow_revert() {
+        let mut bundle = bundle();
+        let hashes = bundle.tx_hashes();
+        assert_eq!(bundle.reverting_tx_hashes(), vec![hashes[1]]);
+
+        assert!(bundle.set_allow_revert(&hashes[0], true));
+        assert_eq!(bundle.reverting_tx_hashes(), hashes);
+        assert!(bundle.set_allow_revert(&hashes[1], false));
+        assert_eq!(bundle.reverting_tx_hashes(), vec![hashes[0]]);
+        assert!(bundle.set_allow_revert(&hashes[0], false));
+        assert!(bundle.reverting_tx_hashes().is_empty());
+
+        assert!(!bundle.set_allow_revert(&SH256::default(), true));
+        assert!(bundle.reverting_tx_hashes().is_empty());
+    }
+
+    #[test]
+    fn test_from_bytes_unrecoverable_sender() {
+        let err = rlp::DecoderError::Custom("invalid tx signature");
+        // signed for chain 1
+        let other_chain = Signer::new(SU256::from(5_u64));
+        let tx = PoolTx::with_tx(&signer(), decode_tx(LEGACY_TX));
+        assert_eq!(
+            PoolTx::from_bytes(&other_chain, &tx.to_bytes()).unwrap_err(),
+            err
+        );
+        let bundle = bundle();
+        assert_eq!(
+            Bundle::from_bytes(&other_chain, &bundle.to_bytes()).unwrap_err(),
+            err
+        );
+        for item in [PoolItem::Tx(tx), PoolItem::Bundle(bundle)] {
+            assert_eq!(
+                PoolItem::from_bytes(&other_chain, &item.to_bytes()).unwrap_err(),
+                err
+            );
+        }
+    }
 }
@@ -3,8 +3,8 @@ use std::prelude::v1::*;
 use serde::de::DeserializeOwned;
 
 use crate::{
-    Block, BlockHeader, BlockHeaderTrait, BlockTrait, Receipt, ReceiptTrait, Transaction,
-    TransactionInner, TxTrait, Withdrawal,
+    Block, BlockHeader, BlockHeaderTrait, BlockTrait, Bloom, KeccakHasher, Receipt, ReceiptTrait,
+    Transaction, TransactionInner, TxTrait, Withdrawal, SH256,
 };
 
 pub trait EngineTypes: std::fmt::Debug + Clone + Send + 'static {
@@ -27,3 +27,81 @@ impl EngineTypes for EthereumEngineTypes {
     type Withdrawal = Withdrawal;
     type RpcTransaction = Transaction;
 }
+
+pub fn receipts_root<E: EngineTypes>(receipts: &[E::Receipt]) -> SH256 {
+    if receipts.len() == 0 {
+        "0x56e81f171bcc55a6ff8345e692c0f86e5b48e01b996cadc001622fb5e363b421".into()
+    } else {
+        let rs: Vec<_> = receipts.iter().map(|r| r.rlp_bytes()).collect();
+        triehash::ordered_trie_root::<KeccakHasher, _>(rs).into()
+    }
+}
+
+pub fn block_bloom<E: EngineTypes>(receipts: &[E::Receipt]) -> Bloom {
+    let mut bloom = Bloom::new();
+    for receipt in receipts {
+        bloom.add_logs(receipt.logs());
+    }
+    bloom
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tx::tests::{decode_tx, DYNAMIC_FEE_TX, LEGACY_TX};
+    use crate::{create_bloom, Log};
+    use std::sync::Arc;
+
+    fn receipt(ty: u64, gas: u64, address: &str) -> Receipt {
+        let log: Log = serde_json::from_value(serde_json::json!({
+            "address": address,
+            "topics": ["0xddf252ad1be2c89b69c2b068fc378daa952ba7f163c4a11628f55a4df523b3ef"],
+            "data": "0x",
+            "blockNumber": "0x1",
+            "transactionHash": "0x0000000000000000000000000000000000000000000000000000000000000000",
+            "transactionIndex": "0x0",
+            "blockHash": "0x0000000000000000000000000000000000000000000000000000000000000000",
+            "logIndex": "0x0",
+            "removed": false,
+        }))
+        .unwrap();
+        let mut bloom = Bloom::new();
+        bloom.add_logs(std::slice::from_ref(&log));
+        Receipt {
+            r#type: Some(ty.into()),
+            status: 1_u64.into(),
+            cumulative_gas_used: gas.into(),
+            logs_bloom: bloom.to_hex(),
+            logs: vec![log],
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_generic_matches_concrete() {
+        let receipts = vec![
+            receipt(0, 21000, "0x3535353535353535353535353535353535353535"),
+            receipt(2, 42000, "0x9d8a62f656a8d1615c1294fd71e9cfb3e4855a4f"),
+        ];
+        let txs = vec![
+            Arc::new(decode_tx(LEGACY_TX)),
+            Arc::new(decode_tx(DYNAMIC_FEE_TX)),
+        ];
+        let block = Block::new(BlockHeader::default(), txs, &receipts, None);
+
+        assert_eq!(
+            receipts_root::<EthereumEngineTypes>(&receipts),
+            block.header.receipts_root
+        );
+        let bloom = block_bloom::<EthereumEngineTypes>(&receipts);
+        assert_eq!(bloom, create_bloom(receipts.iter()));
+        assert_eq!(bloom.to_hex(), block.header.logs_bloom);
+
+        let empty = Block::new(BlockHeader::default(), Vec::new(), &[], None);
+        assert_eq!(
+            receipts_root::<EthereumEngineTypes>(&[]),
+            empty.header.receipts_root
+        );
+        assert_eq!(block_bloom::<EthereumEngineTypes>(&[]), Bloom::new());
+    }
+}
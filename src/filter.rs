@@ -0,0 +1,203 @@
+use std::prelude::v1::*;
+
+use crate::{BlockSelector, Bloom, Log, SH160, SH256};
+use serde::{Deserialize, Deserializer, Serialize};
+
+// The `eth_getLogs` filter object. The block range defaults to "latest" on
+// the node side, `address` and every `topics` slot accept either a single
+// value or a list, and a null topic slot is a wildcard.
+#[derive(Clone, Debug, Default, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct LogFilter {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub from_block: Option<BlockSelector>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub to_block: Option<BlockSelector>,
+    #[serde(
+        default,
+        deserialize_with = "deserialize_address",
+        skip_serializing_if = "Vec::is_empty"
+    )]
+    pub address: Vec<SH160>,
+    #[serde(
+        default,
+        deserialize_with = "deserialize_topics",
+        skip_serializing_if = "Vec::is_empty"
+    )]
+    pub topics: Vec<Option<Vec<SH256>>>,
+}
+
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum OneOrMany<T> {
+    One(T),
+    Many(Vec<T>),
+}
+
+impl<T> From<OneOrMany<T>> for Vec<T> {
+    fn from(val: OneOrMany<T>) -> Self {
+        match val {
+            OneOrMany::One(val) => vec![val],
+            OneOrMany::Many(val) => val,
+        }
+    }
+}
+
+fn deserialize_address<'de, D>(deserializer: D) -> Result<Vec<SH160>, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    let address: Option<OneOrMany<SH160>> = Deserialize::deserialize(deserializer)?;
+    Ok(address.map(Vec::from).unwrap_or_default())
+}
+
+fn deserialize_topics<'de, D>(deserializer: D) -> Result<Vec<Option<Vec<SH256>>>, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    let topics: Option<Vec<Option<OneOrMany<SH256>>>> = Deserialize::deserialize(deserializer)?;
+    Ok(topics
+        .unwrap_or_default()
+        .into_iter()
+        .map(|topic| topic.map(Vec::from))
+        .collect())
+}
+
+impl LogFilter {
+    pub fn matches(&self, log: &Log) -> bool {
+        if !self.address.is_empty() && !self.address.contains(&log.address) {
+            return false;
+        }
+        for (idx, topics) in self.topics.iter().enumerate() {
+            let topics = match topics {
+                Some(topics) if !topics.is_empty() => topics,
+                _ => continue, // wildcard
+            };
+            match log.topics.get(idx) {
+                Some(topic) if topics.contains(topic) => {}
+                _ => return false,
+            }
+        }
+        true
+    }
+
+    pub fn matches_bloom(&self, bloom: &Bloom) -> bool {
+        if !self.address.is_empty()
+            && !self
+                .address
+                .iter()
+                .any(|addr| bloom.contains(addr.as_bytes()))
+        {
+            return false;
+        }
+        for topics in &self.topics {
+            let topics = match topics {
+                Some(topics) if !topics.is_empty() => topics,
+                _ => continue, // wildcard
+            };
+            if !topics.iter().any(|topic| bloom.contains(topic.as_bytes())) {
+                return false;
+            }
+        }
+        true
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    const ADDR: &str = "0xb59f67a8bff5d8cd03f6ac17265c550ed8f33907";
+    const TOPIC0: &str = "0xddf252ad1be2c89b69c2b068fc378daa952ba7f163c4a11628f55a4df523b3ef";
+    const TOPIC1: &str = "0x00000000000000000000000000000000000000000000000000000000000000aa";
+
+    fn log(topics: &[&str]) -> Log {
+        serde_json::from_value(json!({
+            "address": ADDR,
+            "topics": topics,
+            "data": "0x",
+            "blockNumber": "0x1",
+            "transactionHash": TOPIC1,
+            "transactionIndex": "0x0",
+            "blockHash": TOPIC1,
+            "logIndex": "0x0",
+            "removed": false,
+        }))
+        .unwrap()
+    }
+
+    #[test]
+    fn test_deserialize_minimal() {
+        let filter: LogFilter =
+            serde_json::from_value(json!({"address": ADDR, "topics": [TOPIC0]})).unwrap();
+        assert_eq!(filter.from_block, None);
+        assert_eq!(filter.to_block, None);
+        assert_eq!(filter.address, vec![SH160::from(ADDR)]);
+        assert_eq!(filter.topics, vec![Some(vec![SH256::from(TOPIC0)])]);
+
+        let filter: LogFilter = serde_json::from_value(json!({})).unwrap();
+        assert!(filter.address.is_empty() && filter.topics.is_empty());
+        let filter: LogFilter =
+            serde_json::from_value(json!({"address": null, "topics": null})).unwrap();
+        assert!(filter.address.is_empty() && filter.topics.is_empty());
+    }
+
+    #[test]
+    fn test_deserialize_mixed_topics() {
+        let filter: LogFilter = serde_json::from_value(json!({
+            "fromBlock": "0x10",
+            "toBlock": "latest",
+            "address": [ADDR],
+            "topics": [null, TOPIC1, [TOPIC0, TOPIC1]],
+        }))
+        .unwrap();
+        assert_eq!(filter.from_block, Some(BlockSelector::Number(0x10.into())));
+        assert_eq!(filter.to_block, Some(BlockSelector::Latest));
+        assert_eq!(
+            filter.topics,
+            vec![
+                None,
+                Some(vec![TOPIC1.into()]),
+                Some(vec![TOPIC0.into(), TOPIC1.into()]),
+            ]
+        );
+        assert!(serde_json::from_value::<LogFilter>(json!({"address": 1})).is_err());
+    }
+
+    #[test]
+    fn test_serialize_skips_empty() {
+        let filter = LogFilter::default();
+        assert_eq!(serde_json::to_value(&filter).unwrap(), json!({}));
+
+        let filter = LogFilter {
+            to_block: Some(BlockSelector::Latest),
+            topics: vec![None, Some(vec![TOPIC0.into()])],
+            ..Default::default()
+        };
+        let json = serde_json::to_value(&filter).unwrap();
+        assert_eq!(
+            json,
+            json!({"toBlock": "latest", "topics": [null, [TOPIC0]]})
+        );
+        let got: LogFilter = serde_json::from_value(json).unwrap();
+        assert_eq!(got.topics, filter.topics);
+    }
+
+    #[test]
+    fn test_matches() {
+        let filter: LogFilter = serde_json::from_value(json!({
+            "address": ADDR,
+            "topics": [TOPIC0, null, [TOPIC1]],
+        }))
+        .unwrap();
+        assert!(filter.matches(&log(&[TOPIC0, TOPIC0, TOPIC1])));
+        assert!(!filter.matches(&log(&[TOPIC0, TOPIC0])));
+        assert!(!filter.matches(&log(&[TOPIC1, TOPIC0, TOPIC1])));
+
+        let mut bloom = Bloom::new();
+        bloom.add_logs(&[log(&[TOPIC0, TOPIC0, TOPIC1])]);
+        assert!(filter.matches_bloom(&bloom));
+        assert!(!filter.matches_bloom(&Bloom::new()));
+    }
+}
@@ -0,0 +1,27 @@
+use std::prelude::v1::*;
+
+use serde::{Deserialize, Serialize};
+
+// Mainnet hard forks in activation order.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash, Deserialize, Serialize)]
+pub enum Fork {
+    Frontier,
+    Homestead,
+    TangerineWhistle,
+    SpuriousDragon,
+    Byzantium,
+    Constantinople,
+    Petersburg,
+    Istanbul,
+    Berlin,
+    London,
+    Paris,
+    Shanghai,
+    Cancun,
+}
+
+impl Default for Fork {
+    fn default() -> Self {
+        Self::Frontier
+    }
+}
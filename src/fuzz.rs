@@ -0,0 +1,180 @@
+use std::prelude::v1::*;
+
+use crate::{
+    AccessListTx, BlockHeader, BlockNonce, DynamicFeeTx, HexBytes, LegacyTx, Log, Nilable, Receipt,
+    TransactionAccessTuple, TransactionInner, SH160, SH256, SU256, SU64, U64,
+};
+use arbitrary::{Arbitrary, Result, Unstructured};
+
+impl<'a> Arbitrary<'a> for SH256 {
+    fn arbitrary(u: &mut Unstructured<'a>) -> Result<Self> {
+        Ok(SH256::from_slice(&<[u8; 32]>::arbitrary(u)?))
+    }
+}
+
+impl<'a> Arbitrary<'a> for SH160 {
+    fn arbitrary(u: &mut Unstructured<'a>) -> Result<Self> {
+        Ok(SH160::from_slice(&<[u8; 20]>::arbitrary(u)?))
+    }
+}
+
+impl<'a> Arbitrary<'a> for SU256 {
+    fn arbitrary(u: &mut Unstructured<'a>) -> Result<Self> {
+        Ok(<[u8; 32]>::arbitrary(u)?.into())
+    }
+}
+
+impl<'a> Arbitrary<'a> for SU64 {
+    fn arbitrary(u: &mut Unstructured<'a>) -> Result<Self> {
+        Ok(u64::arbitrary(u)?.into())
+    }
+}
+
+fn arbitrary_bytes(u: &mut Unstructured) -> Result<HexBytes> {
+    Ok(Vec::<u8>::arbitrary(u)?.into())
+}
+
+impl<'a> Arbitrary<'a> for TransactionAccessTuple {
+    fn arbitrary(u: &mut Unstructured<'a>) -> Result<Self> {
+        Ok(Self {
+            address: u.arbitrary()?,
+            storage_keys: u.arbitrary()?,
+        })
+    }
+}
+
+impl<'a> Arbitrary<'a> for TransactionInner {
+    fn arbitrary(u: &mut Unstructured<'a>) -> Result<Self> {
+        let to: Nilable<SH160> = Option::<SH160>::arbitrary(u)?.into();
+        // typed transactions carry the bare parity
+        let parity: SU256 = u64::from(bool::arbitrary(u)?).into();
+        Ok(match u.int_in_range(0..=2)? {
+            0 => Self::Legacy(LegacyTx {
+                nonce: u.arbitrary()?,
+                gas_price: u.arbitrary()?,
+                gas: u.arbitrary()?,
+                to,
+                value: u.arbitrary()?,
+                data: arbitrary_bytes(u)?,
+                v: u.arbitrary()?,
+                r: u.arbitrary()?,
+                s: u.arbitrary()?,
+            }),
+            1 => Self::AccessList(AccessListTx {
+                chain_id: u.arbitrary()?,
+                nonce: u.arbitrary()?,
+                gas_price: u.arbitrary()?,
+                gas: u.arbitrary()?,
+                to,
+                value: u.arbitrary()?,
+                data: arbitrary_bytes(u)?,
+                access_list: u.arbitrary()?,
+                v: parity,
+                r: u.arbitrary()?,
+                s: u.arbitrary()?,
+            }),
+            _ => Self::DynamicFee(DynamicFeeTx {
+                chain_id: u.arbitrary()?,
+                nonce: u.arbitrary()?,
+                max_priority_fee_per_gas: u.arbitrary()?,
+                max_fee_per_gas: u.arbitrary()?,
+                gas: u.arbitrary()?,
+                to,
+                value: u.arbitrary()?,
+                data: arbitrary_bytes(u)?,
+                access_list: u.arbitrary()?,
+                v: parity,
+                r: u.arbitrary()?,
+                s: u.arbitrary()?,
+            }),
+        })
+    }
+}
+
+impl<'a> Arbitrary<'a> for BlockHeader {
+    fn arbitrary(u: &mut Unstructured<'a>) -> Result<Self> {
+        Ok(Self {
+            parent_hash: u.arbitrary()?,
+            sha3_uncles: u.arbitrary()?,
+            miner: u.arbitrary()?,
+            state_root: u.arbitrary()?,
+            transactions_root: u.arbitrary()?,
+            receipts_root: u.arbitrary()?,
+            logs_bloom: <[u8; 256]>::arbitrary(u)?.to_vec().into(),
+            difficulty: u.arbitrary()?,
+            number: u.arbitrary()?,
+            gas_limit: u.arbitrary()?,
+            gas_used: u.arbitrary()?,
+            timestamp: u.arbitrary()?,
+            extra_data: arbitrary_bytes(u)?,
+            mix_hash: u.arbitrary()?,
+            nonce: BlockNonce::from(U64::from(u64::arbitrary(u)?)),
+            base_fee_per_gas: u.arbitrary()?,
+            withdrawals_root: u.arbitrary()?,
+        })
+    }
+}
+
+impl<'a> Arbitrary<'a> for Log {
+    fn arbitrary(u: &mut Unstructured<'a>) -> Result<Self> {
+        Ok(Self {
+            address: u.arbitrary()?,
+            topics: u.arbitrary()?,
+            data: arbitrary_bytes(u)?,
+            block_number: u.arbitrary()?,
+            transaction_hash: u.arbitrary()?,
+            transaction_index: u.arbitrary()?,
+            block_hash: u.arbitrary()?,
+            log_index: u.arbitrary()?,
+            removed: u.arbitrary()?,
+        })
+    }
+}
+
+impl<'a> Arbitrary<'a> for Receipt {
+    fn arbitrary(u: &mut Unstructured<'a>) -> Result<Self> {
+        let logs: Vec<Log> = u.arbitrary()?;
+        let mut bloom = crate::Bloom::new();
+        bloom.add_logs(&logs);
+        Ok(Self {
+            r#type: Some(u.int_in_range(0..=2_u64)?.into()),
+            status: u64::from(bool::arbitrary(u)?).into(),
+            cumulative_gas_used: u.arbitrary()?,
+            logs_bloom: bloom.to_hex(),
+            logs,
+            transaction_hash: u.arbitrary()?,
+            gas_used: u.arbitrary()?,
+            ..Default::default()
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_arbitrary_tx_roundtrip() {
+        let signer = crate::Signer::new(SU256::from(1_u64));
+        // a fixed xorshift stream keeps the generated transactions stable
+        let mut state = 0x2545f4914f6cdd1d_u64;
+        let data: Vec<u8> = (0..100 * 1024)
+            .map(|_| {
+                state ^= state << 13;
+                state ^= state >> 7;
+                state ^= state << 17;
+                state as u8
+            })
+            .collect();
+        for chunk in data.chunks(1024) {
+            let tx = TransactionInner::arbitrary(&mut Unstructured::new(chunk)).unwrap();
+            let encoded = rlp::encode(&tx);
+            assert_eq!(rlp::decode::<TransactionInner>(&encoded), Ok(tx.clone()));
+            assert_eq!(TransactionInner::from_bytes(&tx.to_bytes()), Ok(tx.clone()));
+            let json = serde_json::to_string(&tx).unwrap();
+            assert_eq!(serde_json::from_str::<TransactionInner>(&json).unwrap(), tx);
+            // random signatures must be rejected, not panic
+            let _ = signer.try_sender(&tx);
+        }
+    }
+}
@@ -28,4 +28,21 @@ pub use traits::*;
 mod serde_signer;
 pub use serde_signer::*;
 mod engine_types;
-pub use engine_types::*;
\ No newline at end of file
+pub use engine_types::*;
+mod filter;
+pub use filter::*;
+mod typed_data;
+pub use typed_data::*;
+mod fork;
+pub use fork::*;
+mod trie;
+#[cfg(feature = "arbitrary")]
+mod fuzz;
+#[cfg(feature = "testing")]
+mod testing;
+#[cfg(feature = "testing")]
+pub use testing::*;
+#[cfg(feature = "optimism")]
+mod optimism;
+#[cfg(feature = "optimism")]
+pub use optimism::*;
\ No newline at end of file
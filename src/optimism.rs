@@ -0,0 +1,319 @@
+use std::prelude::v1::*;
+
+use crate::{
+    Block, BlockHeader, EngineTypes, Hasher, Nilable, Receipt, Signer, Transaction,
+    TransactionAccessTuple, TransactionInner, TxTrait, Withdrawal, SH160, SH256, SU256, SU64,
+};
+use crypto::{keccak_hash, Secp256k1PrivateKey};
+use hex::HexBytes;
+use rlp_derive::{RlpDecodable, RlpEncodable};
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+
+pub const DEPOSIT_TX_TYPE: u8 = 0x7e;
+
+// deposits pay no fees, the fee accessors of TxTrait hand out references
+static ZERO: SU256 = SU256::zero();
+
+#[derive(
+    Default, Clone, Debug, Deserialize, Serialize, RlpEncodable, RlpDecodable, PartialEq, Eq,
+)]
+#[serde(rename_all = "camelCase")]
+pub struct DepositTx {
+    pub source_hash: SH256,
+    pub from: SH160,
+    pub to: Nilable<SH160>, // nil means contract creation
+    pub mint: SU256,        // wei minted on L2, locked on L1
+    pub value: SU256,
+    pub gas: SU64,
+    pub is_system_tx: bool,
+    pub data: HexBytes,
+}
+
+impl DepositTx {
+    // Deposits are authenticated by L1 derivation rather than a signature.
+    pub fn sender(&self) -> SH160 {
+        self.from
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum OptimismTransaction {
+    Ethereum(TransactionInner),
+    Deposit(DepositTx),
+}
+
+impl From<TransactionInner> for OptimismTransaction {
+    fn from(tx: TransactionInner) -> Self {
+        Self::Ethereum(tx)
+    }
+}
+
+impl From<DepositTx> for OptimismTransaction {
+    fn from(tx: DepositTx) -> Self {
+        Self::Deposit(tx)
+    }
+}
+
+impl rlp::Encodable for OptimismTransaction {
+    fn rlp_append(&self, s: &mut rlp::RlpStream) {
+        match self {
+            Self::Ethereum(tx) => tx.rlp_append(s),
+            Self::Deposit(tx) => {
+                const PREFIX: [u8; 1] = [DEPOSIT_TX_TYPE];
+                s.append_raw(&PREFIX, 0);
+                tx.rlp_append(s);
+            }
+        }
+    }
+}
+
+impl rlp::Decodable for OptimismTransaction {
+    fn decode(rlp: &rlp::Rlp) -> Result<Self, rlp::DecoderError> {
+        let n = rlp.as_raw();
+        match n.first() {
+            Some(&DEPOSIT_TX_TYPE) => Ok(Self::Deposit(rlp::decode(&n[1..])?)),
+            _ => Ok(Self::Ethereum(TransactionInner::decode(rlp)?)),
+        }
+    }
+}
+
+impl Serialize for OptimismTransaction {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: serde::Serializer,
+    {
+        let tx: HexBytes = self.to_bytes().into();
+        serializer.serialize_str(&format!("{}", tx))
+    }
+}
+
+impl<'de> Deserialize<'de> for OptimismTransaction {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: serde::Deserializer<'de>,
+    {
+        let s: HexBytes = Deserialize::deserialize(deserializer)?;
+        OptimismTransaction::from_bytes(&s)
+            .map_err(|err| serde::de::Error::custom(format!("{:?}", err)))
+    }
+}
+
+impl Hasher for OptimismTransaction {
+    fn hash(&self) -> SH256 {
+        keccak_hash(&self.to_bytes()).into()
+    }
+}
+
+impl OptimismTransaction {
+    pub fn to_bytes(&self) -> Vec<u8> {
+        rlp::encode(self).to_vec()
+    }
+
+    pub fn from_bytes(data: &[u8]) -> Result<Self, rlp::DecoderError> {
+        rlp::decode(data)
+    }
+
+    pub fn is_deposit(&self) -> bool {
+        matches!(self, Self::Deposit(_))
+    }
+}
+
+impl TxTrait for OptimismTransaction {
+    fn legacy(
+        nonce: SU64,
+        gas_price: SU256,
+        gas: SU64,
+        to: Option<SH160>,
+        value: SU256,
+        data: HexBytes,
+    ) -> Self {
+        Self::Ethereum(<TransactionInner as TxTrait>::legacy(
+            nonce, gas_price, gas, to, value, data,
+        ))
+    }
+    fn gas_price(&self, base_fee: Option<SU256>) -> SU256 {
+        match self {
+            Self::Ethereum(tx) => TxTrait::gas_price(tx, base_fee),
+            Self::Deposit(_) => SU256::zero(),
+        }
+    }
+    fn max_priority_fee_per_gas(&self) -> &SU256 {
+        match self {
+            Self::Ethereum(tx) => TxTrait::max_priority_fee_per_gas(tx),
+            Self::Deposit(_) => &ZERO,
+        }
+    }
+    fn gas(&self) -> SU64 {
+        match self {
+            Self::Ethereum(tx) => TxTrait::gas(tx),
+            Self::Deposit(tx) => tx.gas,
+        }
+    }
+    fn hash(&self) -> SH256 {
+        Hasher::hash(self)
+    }
+    fn max_fee_per_gas(&self) -> &SU256 {
+        match self {
+            Self::Ethereum(tx) => TxTrait::max_fee_per_gas(tx),
+            Self::Deposit(_) => &ZERO,
+        }
+    }
+    fn to(&self) -> Option<SH160> {
+        match self {
+            Self::Ethereum(tx) => TxTrait::to(tx),
+            Self::Deposit(tx) => tx.to.0,
+        }
+    }
+    fn value(&self) -> SU256 {
+        match self {
+            Self::Ethereum(tx) => TxTrait::value(tx),
+            Self::Deposit(tx) => tx.value,
+        }
+    }
+    fn input(&self) -> &[u8] {
+        match self {
+            Self::Ethereum(tx) => TxTrait::input(tx),
+            Self::Deposit(tx) => &tx.data,
+        }
+    }
+    fn nonce(&self) -> u64 {
+        match self {
+            Self::Ethereum(tx) => TxTrait::nonce(tx),
+            // the nonce of a deposit is assigned by the sequencer at execution
+            Self::Deposit(_) => 0,
+        }
+    }
+    fn access_list(&self) -> Option<&[TransactionAccessTuple]> {
+        match self {
+            Self::Ethereum(tx) => TxTrait::access_list(tx),
+            Self::Deposit(_) => None,
+        }
+    }
+    fn gas_limit(&self) -> u64 {
+        match self {
+            Self::Ethereum(tx) => TxTrait::gas_limit(tx),
+            Self::Deposit(tx) => tx.gas.as_u64(),
+        }
+    }
+    fn sender(&self, signer: &Signer) -> SH160 {
+        match self {
+            Self::Ethereum(tx) => TxTrait::sender(tx, signer),
+            Self::Deposit(tx) => tx.sender(),
+        }
+    }
+    fn sign(&mut self, prvkey: &Secp256k1PrivateKey, chain_id: u64) {
+        match self {
+            Self::Ethereum(tx) => TxTrait::sign(tx, prvkey, chain_id),
+            // deposits carry no signature
+            Self::Deposit(_) => {}
+        }
+    }
+    fn to_json_map(&self) -> Map<String, Value> {
+        match self {
+            Self::Ethereum(tx) => TxTrait::to_json_map(tx),
+            Self::Deposit(tx) => match serde_json::to_value(&tx).unwrap() {
+                Value::Object(mut n) => {
+                    n.insert("type".into(), format!("{:#x}", DEPOSIT_TX_TYPE).into());
+                    n
+                }
+                _ => unreachable!(),
+            },
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct OptimismEngineTypes;
+
+impl EngineTypes for OptimismEngineTypes {
+    type Block = Block;
+    type BlockHeader = BlockHeader;
+    type Receipt = Receipt;
+    type Transaction = OptimismTransaction;
+    type Withdrawal = Withdrawal;
+    type RpcTransaction = Transaction;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tx::tests::{decode_tx, signer, DYNAMIC_FEE_TX, LEGACY_TX, SENDER};
+
+    const DEPOSIT_TX: &str = "0x7ef867a00101010101010101010101010101010101010101010101010101010101010101949d8a62f656a8d1615c1294fd71e9cfb3e4855a4f943535353535353535353535353535353535353535880de0b6b3a76400008806f05b59d3b20000830186a08084deadbeef";
+    // contract creation by a system deposit
+    const SYSTEM_DEPOSIT_TX: &str = "0x7ef853a00101010101010101010101010101010101010101010101010101010101010101949d8a62f656a8d1615c1294fd71e9cfb3e4855a4f80880de0b6b3a76400008806f05b59d3b20000830186a00184deadbeef";
+
+    fn deposit() -> DepositTx {
+        DepositTx {
+            source_hash: SH256::from([1_u8; 32]),
+            from: SENDER.into(),
+            to: Some("0x3535353535353535353535353535353535353535".into()).into(),
+            mint: SU256::from(1_000_000_000_000_000_000_u64),
+            value: SU256::from(500_000_000_000_000_000_u64),
+            gas: 100_000_u64.into(),
+            is_system_tx: false,
+            data: hex::decode("deadbeef").unwrap().into(),
+        }
+    }
+
+    fn decode(raw: &str) -> OptimismTransaction {
+        OptimismTransaction::from_bytes(&hex::decode(&raw[2..]).unwrap()).unwrap()
+    }
+
+    #[test]
+    fn test_deposit_tx_rlp() {
+        let tx = OptimismTransaction::from(deposit());
+        assert_eq!(HexBytes::from(tx.to_bytes()).to_string(), DEPOSIT_TX);
+        assert_eq!(decode(DEPOSIT_TX), tx);
+        assert!(tx.is_deposit());
+        assert_eq!(
+            Hasher::hash(&tx),
+            "0xb3f8e84f84426349aa63e0294b34b87d334b8ca066bf45b43cc2f0aa980cbe74".into()
+        );
+
+        let mut system = deposit();
+        system.to = None.into();
+        system.is_system_tx = true;
+        let tx = OptimismTransaction::from(system);
+        assert_eq!(decode(SYSTEM_DEPOSIT_TX), tx);
+        assert_eq!(
+            TxTrait::hash(&tx),
+            "0x30974104e2734cd7858e1c045eff64cd75f922e1719a45b0fd1956b73994e488".into()
+        );
+        assert_eq!(TxTrait::to(&tx), None);
+
+        let json = serde_json::to_value(&tx).unwrap();
+        assert_eq!(json, SYSTEM_DEPOSIT_TX);
+        assert_eq!(
+            serde_json::from_value::<OptimismTransaction>(json).unwrap(),
+            tx
+        );
+        assert_eq!(tx.to_json_map()["type"], "0x7e");
+    }
+
+    #[test]
+    fn test_deposit_tx_sender() {
+        let tx = decode(DEPOSIT_TX);
+        // no signature to recover, whatever the chain of the signer
+        assert_eq!(TxTrait::sender(&tx, &signer()), SENDER.into());
+        assert_eq!(
+            TxTrait::sender(&tx, &Signer::new(10_u64.into())),
+            SENDER.into()
+        );
+        assert_eq!(TxTrait::gas_price(&tx, Some(1_u64.into())), SU256::zero());
+        assert_eq!(TxTrait::nonce(&tx), 0);
+    }
+
+    #[test]
+    fn test_ethereum_tx_passthrough() {
+        for raw in [LEGACY_TX, DYNAMIC_FEE_TX] {
+            let tx = decode(raw);
+            assert!(!tx.is_deposit());
+            assert_eq!(tx, OptimismTransaction::from(decode_tx(raw)));
+            assert_eq!(HexBytes::from(tx.to_bytes()).to_string(), raw);
+            assert_eq!(TxTrait::hash(&tx), TxTrait::hash(&decode_tx(raw)));
+            assert_eq!(TxTrait::sender(&tx, &signer()), SENDER.into());
+        }
+    }
+}
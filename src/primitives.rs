@@ -1,6 +1,6 @@
 use std::prelude::v1::*;
 
-use ethereum_types::FromStrRadixErr;
+use ethereum_types::{FromDecStrErr, FromStrRadixErr};
 pub use ethereum_types::{H160, H256, U256, U64};
 use hex::FromHexError;
 use serde::{de::Error, Deserialize, Deserializer, Serializer};
@@ -189,7 +189,7 @@ macro_rules! impl_type_from {
 
 macro_rules! impl_wrap_type {
     ($wrap:ident, $ori:ty, $array:ty, $deser:ident, $ser:ident) => {
-        #[derive(Clone, Default, PartialEq, Eq, Copy)]
+        #[derive(Clone, Default, PartialEq, Eq, Copy, Hash)]
         pub struct $wrap($ori);
         impl core::ops::Deref for $wrap {
             type Target = $ori;
@@ -289,6 +289,20 @@ macro_rules! impl_asref {
                 <$ori>::from_slice(slice).into()
             }
         }
+        impl core::convert::TryFrom<&[u8]> for $name {
+            type Error = String;
+            fn try_from(slice: &[u8]) -> Result<Self, Self::Error> {
+                if slice.len() != <$ori>::len_bytes() {
+                    return Err(format!(
+                        "invalid length for {}: expect {}, got {}",
+                        stringify!($name),
+                        <$ori>::len_bytes(),
+                        slice.len()
+                    ));
+                }
+                Ok(Self::from_slice(slice))
+            }
+        }
     };
 }
 
@@ -298,6 +312,23 @@ impl_wrap_rlp!(SH256, H256);
 impl_ssz_type!(SH256, [u8; 32]);
 impl_asref!(SH256, H256);
 
+impl SH256 {
+    pub const fn zero() -> SH256 {
+        SH256(H256([0_u8; 32]))
+    }
+    // unlike `From<&str>`, the hash must be exactly 32 bytes
+    pub fn try_from_str(val: &str) -> Result<SH256, FromHexError> {
+        Ok(H256::from_slice(&parse_exact_bytes(val, 32)?).into())
+    }
+    pub fn is_zero(&self) -> bool {
+        self.0.is_zero()
+    }
+    /// Interprets the hash as a left-padded address, as used by indexed event parameters
+    pub fn as_address(&self) -> SH160 {
+        SH160(H160::from_slice(&self.0[12..]))
+    }
+}
+
 impl From<&str> for SH256 {
     fn from(val: &str) -> Self {
         parse_string_h256(val).unwrap().into()
@@ -316,6 +347,34 @@ impl From<&str> for SU256 {
     }
 }
 
+impl std::str::FromStr for SH256 {
+    type Err = FromHexError;
+    fn from_str(val: &str) -> Result<Self, Self::Err> {
+        Self::try_from_str(val)
+    }
+}
+
+impl std::str::FromStr for SH160 {
+    type Err = FromHexError;
+    fn from_str(val: &str) -> Result<Self, Self::Err> {
+        Self::try_from_str(val)
+    }
+}
+
+impl std::str::FromStr for SU256 {
+    type Err = FromStrRadixErr;
+    fn from_str(val: &str) -> Result<Self, Self::Err> {
+        Ok(parse_string_u256(non_empty_number(val)?)?.into())
+    }
+}
+
+impl std::str::FromStr for SU64 {
+    type Err = FromStrRadixErr;
+    fn from_str(val: &str) -> Result<Self, Self::Err> {
+        Ok(parse_string_u64(non_empty_number(val)?)?.into())
+    }
+}
+
 impl From<i32> for SU256 {
     fn from(val: i32) -> Self {
         let val: U256 = val.into();
@@ -336,6 +395,19 @@ impl_ssz_type!(SH160, [u8; 20]);
 impl_wrap_cmp!(SH160, H160);
 impl_asref!(SH160, H160);
 
+impl SH160 {
+    pub const fn zero() -> SH160 {
+        SH160(H160([0_u8; 20]))
+    }
+    // unlike `From<&str>`, the address must be exactly 20 bytes
+    pub fn try_from_str(val: &str) -> Result<SH160, FromHexError> {
+        Ok(H160::from_slice(&parse_exact_bytes(val, 20)?).into())
+    }
+    pub fn is_zero(&self) -> bool {
+        self.0.is_zero()
+    }
+}
+
 impl From<&SH160> for SH256 {
     fn from(addr: &SH160) -> Self {
         let mut new = Self::default();
@@ -373,8 +445,11 @@ impl SU256 {
     pub fn one() -> SU256 {
         1u64.into()
     }
-    pub fn zero() -> SU256 {
-        Self::default()
+    pub const fn zero() -> SU256 {
+        SU256(U256([0_u64; 4]))
+    }
+    pub fn is_zero(&self) -> bool {
+        self.0.is_zero()
     }
     pub fn from_big_endian(slice: &[u8]) -> SU256 {
         U256::from_big_endian(slice).into()
@@ -382,6 +457,42 @@ impl SU256 {
     pub fn from_little_endian(slice: &[u8]) -> SU256 {
         U256::from_little_endian(slice).into()
     }
+    pub fn from_str_radix(val: &str, radix: u32) -> Result<SU256, FromStrRadixErr> {
+        Ok(U256::from_str_radix(non_empty_number(val)?, radix)?.into())
+    }
+    pub fn from_decimal(val: &str) -> Result<SU256, FromStrRadixErr> {
+        Self::from_str_radix(val, 10)
+    }
+    // the 0x prefix is optional
+    pub fn from_hex(val: &str) -> Result<SU256, FromStrRadixErr> {
+        let val = val.strip_prefix("0x").unwrap_or(val);
+        Ok(U256::from_str_radix(non_empty_number(val)?, 16)?.into())
+    }
+
+    // Decimal representation scaled down by `10^decimals`, with the fraction
+    // truncated to `max_places` digits and trailing zeros removed.
+    pub fn format_units(&self, decimals: u32, max_places: Option<u32>) -> String {
+        let unit = U256::exp10(decimals as usize);
+        let (integer, frac) = self.0.div_mod(unit);
+        let mut frac = format!("{:0>width$}", frac.to_string(), width = decimals as usize);
+        if let Some(max_places) = max_places {
+            frac.truncate(max_places as usize);
+        }
+        let frac = frac.trim_end_matches('0');
+        if frac.is_empty() {
+            format!("{}", integer)
+        } else {
+            format!("{}.{}", integer, frac)
+        }
+    }
+
+    pub fn format_ether(&self) -> String {
+        self.format_units(18, None)
+    }
+
+    pub fn format_gwei(&self) -> String {
+        self.format_units(9, None)
+    }
 }
 
 impl PartialEq<u64> for SU256 {
@@ -410,6 +521,46 @@ impl From<SU64> for SU256 {
     }
 }
 
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct ChainId(pub u64);
+
+impl ChainId {
+    pub fn as_u64(&self) -> u64 {
+        self.0
+    }
+    pub fn as_u256(&self) -> SU256 {
+        self.0.into()
+    }
+}
+
+impl From<u64> for ChainId {
+    fn from(val: u64) -> Self {
+        Self(val)
+    }
+}
+
+impl core::convert::TryFrom<SU256> for ChainId {
+    type Error = String;
+    fn try_from(val: SU256) -> Result<Self, Self::Error> {
+        if val.raw().bits() > 64 {
+            return Err(format!("chain id overflows u64: {}", val));
+        }
+        Ok(Self(val.as_u64()))
+    }
+}
+
+impl From<ChainId> for SU256 {
+    fn from(val: ChainId) -> Self {
+        val.as_u256()
+    }
+}
+
+impl std::fmt::Display for ChainId {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.0)
+    }
+}
+
 pub fn serialize_h256<S>(item: &H256, serializer: S) -> Result<S::Ok, S::Error>
 where
     S: Serializer,
@@ -449,6 +600,9 @@ pub fn parse_string_h256(h256_str: &str) -> Result<H256, hex::FromHexError> {
     //  [a5,23,11,00,..,00] <- in the right endianness
 
     let bytes = hex::decode(h256_str.trim_start_matches("0x"))?;
+    if bytes.len() > 32 {
+        return Err(FromHexError::InvalidStringLength);
+    }
     // pad the bytes to 32bytes
     let mut padded_bytes = [0_u8; 32];
     padded_bytes[32 - bytes.len()..].copy_from_slice(&bytes);
@@ -456,6 +610,23 @@ pub fn parse_string_h256(h256_str: &str) -> Result<H256, hex::FromHexError> {
     Ok(H256::from_slice(&padded_bytes))
 }
 
+// The fallible parsers are strict: unlike the serde helpers above they
+// don't treat "" / "0x" as zero, and hashes are not left-padded.
+fn non_empty_number(val: &str) -> Result<&str, FromStrRadixErr> {
+    if val.is_empty() || val == "0x" {
+        return Err(FromDecStrErr::InvalidLength.into());
+    }
+    Ok(val)
+}
+
+fn parse_exact_bytes(val: &str, len: usize) -> Result<Vec<u8>, FromHexError> {
+    let bytes = hex::decode(val.strip_prefix("0x").unwrap_or(val))?;
+    if bytes.len() != len {
+        return Err(FromHexError::InvalidStringLength);
+    }
+    Ok(bytes)
+}
+
 pub fn serialize_u256<S>(item: &U256, serializer: S) -> Result<S::Ok, S::Error>
 where
     S: Serializer,
@@ -472,6 +643,23 @@ where
     serializer.serialize_str(&item_str)
 }
 
+// JSON-RPC QUANTITY: minimal hex, zero is "0x0"
+pub fn serialize_quantity<S, T>(item: &T, serializer: S) -> Result<S::Ok, S::Error>
+where
+    S: Serializer,
+    T: std::fmt::LowerHex,
+{
+    serializer.serialize_str(&format!("0x{:x}", item))
+}
+
+// JSON-RPC DATA: even-length hex, empty is "0x"
+pub fn serialize_data<S>(item: &[u8], serializer: S) -> Result<S::Ok, S::Error>
+where
+    S: Serializer,
+{
+    serializer.serialize_str(&format!("0x{}", hex::encode(item)))
+}
+
 pub fn deserialize_u256<'de, D>(deserializer: D) -> Result<U256, D::Error>
 where
     D: Deserializer<'de>,
@@ -484,6 +672,10 @@ where
 }
 
 pub fn parse_string_u256(u256_str: &str) -> Result<U256, FromStrRadixErr> {
+    // some nodes return "0x" (or nothing) for zero quantities
+    if u256_str.is_empty() || u256_str == "0x" {
+        return Ok(U256::zero());
+    }
     if u256_str.starts_with("0x") {
         if u256_str.len() % 2 == 1 {
             let new_u256_str = "0x0".to_owned() + &u256_str[2..];
@@ -515,6 +707,9 @@ where
 
 pub fn parse_string_h160(h160_str: &str) -> Result<H160, FromHexError> {
     let bytes = hex::decode(h160_str.trim_start_matches("0x"))?;
+    if bytes.len() > 20 {
+        return Err(FromHexError::InvalidStringLength);
+    }
     let mut padded_bytes = [0_u8; 20];
     padded_bytes[20 - bytes.len()..].copy_from_slice(&bytes);
     Ok(H160::from_slice(&padded_bytes))
@@ -551,13 +746,55 @@ where
 }
 
 pub fn parse_string_u64(u64_str: &str) -> Result<U64, FromStrRadixErr> {
+    // some nodes return "0x" (or nothing) for zero quantities
+    if u64_str.is_empty() || u64_str == "0x" {
+        return Ok(U64::zero());
+    }
     if u64_str.starts_with("0x") {
-        U64::from_str_radix(u64_str, 16)
+        if u64_str.len() % 2 == 1 {
+            let new_u64_str = "0x0".to_owned() + &u64_str[2..];
+            U64::from_str_radix(&new_u64_str, 16)
+        } else {
+            U64::from_str_radix(u64_str, 16)
+        }
     } else {
         U64::from_str_radix(u64_str, 10)
     }
 }
 
+// JSON-RPC QUANTITY must be "0x"-prefixed without leading zeros, except "0x0"
+fn check_quantity(s: &str) -> Result<(), String> {
+    let digits = match s.strip_prefix("0x") {
+        Some(digits) => digits,
+        None => return Err(format!("quantity without 0x prefix: {:?}", s)),
+    };
+    if digits.is_empty() {
+        return Err(format!("empty quantity"));
+    }
+    if digits.len() > 1 && digits.starts_with('0') {
+        return Err(format!("quantity with leading zeros: {:?}", s));
+    }
+    Ok(())
+}
+
+pub fn deserialize_u256_strict<'de, D>(deserializer: D) -> Result<SU256, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    let s: String = Deserialize::deserialize(deserializer)?;
+    check_quantity(&s).map_err(Error::custom)?;
+    Ok(parse_string_u256(&s).map_err(Error::custom)?.into())
+}
+
+pub fn deserialize_u64_strict<'de, D>(deserializer: D) -> Result<SU64, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    let s: String = Deserialize::deserialize(deserializer)?;
+    check_quantity(&s).map_err(Error::custom)?;
+    Ok(parse_string_u64(&s).map_err(Error::custom)?.into())
+}
+
 pub fn deserialize_u32<'de, D>(deserializer: D) -> Result<u32, D::Error>
 where
     D: Deserializer<'de>,
@@ -572,3 +809,379 @@ where
     .map_err(Error::custom)?;
     Ok(v)
 }
+
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum NumberOrString {
+    Number(u64),
+    String(String),
+}
+
+// `#[serde(with = "su256_as_number")]`: values fitting in u64 are written as
+// JSON numbers, larger ones as hex strings. Both forms are accepted back.
+pub mod su256_as_number {
+    use super::{parse_string_u256, NumberOrString, SU256, U256};
+    use serde::{de::Error, Deserialize, Deserializer, Serializer};
+    use std::prelude::v1::*;
+
+    pub fn serialize<S>(item: &SU256, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        if item.raw() <= &U256::from(u64::MAX) {
+            serializer.serialize_u64(item.as_u64())
+        } else {
+            serializer.serialize_str(&format!("0x{:x}", item.raw()))
+        }
+    }
+
+    pub fn deserialize<'de, D>(deserializer: D) -> Result<SU256, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        match NumberOrString::deserialize(deserializer)? {
+            NumberOrString::Number(n) => Ok(n.into()),
+            NumberOrString::String(s) => Ok(parse_string_u256(&s).map_err(Error::custom)?.into()),
+        }
+    }
+}
+
+// `#[serde(with = "su64_as_number")]`: like `su256_as_number`, every SU64 fits.
+pub mod su64_as_number {
+    use super::{parse_string_u64, NumberOrString, SU64};
+    use serde::{de::Error, Deserialize, Deserializer, Serializer};
+    use std::prelude::v1::*;
+
+    pub fn serialize<S>(item: &SU64, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        serializer.serialize_u64(item.as_u64())
+    }
+
+    pub fn deserialize<'de, D>(deserializer: D) -> Result<SU64, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        match NumberOrString::deserialize(deserializer)? {
+            NumberOrString::Number(n) => Ok(n.into()),
+            NumberOrString::String(s) => Ok(parse_string_u64(&s).map_err(Error::custom)?.into()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use core::convert::TryFrom;
+    use hex::HexBytes;
+    use serde::{Deserialize, Serialize};
+
+    #[test]
+    fn test_chain_id_conversion() {
+        let id = ChainId::from(5_u64);
+        assert_eq!(id.as_u64(), 5);
+        assert_eq!(id.as_u256(), SU256::from(5_u64));
+        assert_eq!(SU256::from(id), SU256::from(5_u64));
+        assert_eq!(id.to_string(), "5");
+
+        assert_eq!(
+            ChainId::try_from(SU256::from(u64::MAX)),
+            Ok(ChainId(u64::MAX))
+        );
+        let overflow = SU256::from(u64::MAX) + SU256::from(1_u64);
+        assert!(ChainId::try_from(overflow).is_err());
+    }
+
+    #[test]
+    fn test_from_str_strict() {
+        let addr = "0x3535353535353535353535353535353535353535";
+        let hash = "0x33469b22e9f636356c4160a87eb19df52b7412e8eac32a4a55ffe88ea8350788";
+        assert_eq!(addr.parse::<SH160>(), Ok(SH160::from(addr)));
+        assert_eq!(hash.parse::<SH256>(), Ok(SH256::from(hash)));
+        assert_eq!(SH160::try_from_str(&addr[2..]), Ok(SH160::from(addr)));
+        assert_eq!("0x1a".parse::<SU256>().unwrap(), SU256::from(26_u64));
+        assert_eq!("26".parse::<SU256>().unwrap(), SU256::from(26_u64));
+        assert_eq!("0x1a".parse::<SU64>().unwrap(), SU64::from(26_u64));
+        assert_eq!(SU256::from_hex("1a").unwrap(), SU256::from(26_u64));
+        assert_eq!(SU256::from_hex("0x1a").unwrap(), SU256::from(26_u64));
+        assert_eq!(SU256::from_decimal("26").unwrap(), SU256::from(26_u64));
+
+        for val in ["", "0x"] {
+            assert!(val.parse::<SH160>().is_err());
+            assert!(val.parse::<SH256>().is_err());
+            assert!(val.parse::<SU256>().is_err());
+            assert!(val.parse::<SU64>().is_err());
+            assert!(SU256::from_hex(val).is_err());
+            assert!(SU256::from_decimal(val).is_err());
+        }
+        // no left-padding of short or long hashes
+        assert!("0x12".parse::<SH160>().is_err());
+        assert!("0x12".parse::<SH256>().is_err());
+        assert!(SH160::try_from_str(hash).is_err());
+        assert!(SH256::try_from_str(addr).is_err());
+        assert!("0xzz".parse::<SU256>().is_err());
+        assert!("0x1zz".parse::<SU64>().is_err());
+        assert!("0x10000000000000000".parse::<SU64>().is_err());
+
+        // the serde and `From<&str>` paths keep accepting the node quirks
+        assert_eq!(SU256::from("0x"), SU256::zero());
+        assert_eq!(
+            SH160::from("0x12"),
+            SH160::from("0x0000000000000000000000000000000000000012")
+        );
+    }
+
+    #[test]
+    fn test_is_zero() {
+        const ZERO: SH160 = SH160::zero();
+        assert!(ZERO.is_zero());
+        assert_eq!(ZERO, SH160::default());
+        assert!(!SH160::from("0x3535353535353535353535353535353535353535").is_zero());
+        let mut low = [0_u8; 20];
+        low[19] = 1;
+        assert!(!SH160::from(low).is_zero());
+
+        assert!(SH256::zero().is_zero());
+        assert_eq!(SH256::zero(), SH256::default());
+        assert!(!SH256::from([1_u8; 32]).is_zero());
+
+        assert!(SU256::zero().is_zero());
+        assert_eq!(SU256::zero(), SU256::default());
+        assert!(!SU256::from(1_u64).is_zero());
+        assert!(!SU256::from(u64::MAX).is_zero());
+    }
+
+    #[test]
+    fn test_from_bytes() {
+        let addr = [0x35_u8; 20];
+        let hash = [0x33_u8; 32];
+        assert_eq!(
+            SH160::from(addr),
+            SH160::from("0x3535353535353535353535353535353535353535")
+        );
+        assert_eq!(SH160::try_from(&addr[..]), Ok(SH160::from(addr)));
+        assert_eq!(SH256::try_from(&hash[..]), Ok(SH256::from(hash)));
+
+        assert_eq!(
+            SH160::try_from(&hash[..]),
+            Err("invalid length for SH160: expect 20, got 32".into())
+        );
+        assert!(SH256::try_from(&addr[..]).is_err());
+        assert!(SH256::try_from(&hash[..31]).is_err());
+        assert!(SH160::try_from(&[][..]).is_err());
+    }
+
+    #[test]
+    fn test_serialize_quantity_and_data() {
+        #[derive(Serialize)]
+        struct Fields {
+            #[serde(serialize_with = "serialize_quantity")]
+            quantity: u64,
+            #[serde(serialize_with = "serialize_quantity")]
+            big: U256,
+            #[serde(serialize_with = "serialize_data")]
+            data: HexBytes,
+        }
+        let zero = Fields {
+            quantity: 0,
+            big: U256::zero(),
+            data: HexBytes::new(),
+        };
+        assert_eq!(
+            serde_json::to_value(&zero).unwrap(),
+            serde_json::json!({"quantity": "0x0", "big": "0x0", "data": "0x"})
+        );
+        let val = Fields {
+            quantity: 0x100,
+            big: U256::from(1_u64),
+            data: HexBytes::from(&[0_u8, 1][..]),
+        };
+        assert_eq!(
+            serde_json::to_value(&val).unwrap(),
+            serde_json::json!({"quantity": "0x100", "big": "0x1", "data": "0x0001"})
+        );
+
+        // the wrapper types follow the same rules
+        assert_eq!(serde_json::to_value(SU256::zero()).unwrap(), "0x0");
+        assert_eq!(serde_json::to_value(SU64::from(0_u64)).unwrap(), "0x0");
+        assert_eq!(serde_json::to_value(HexBytes::new()).unwrap(), "0x");
+    }
+
+    #[test]
+    fn test_parse_string_zero_forms() {
+        for val in ["", "0x", "0x0", "0x00", "0"] {
+            assert_eq!(parse_string_u256(val).unwrap(), U256::zero(), "{:?}", val);
+            assert_eq!(parse_string_u64(val).unwrap(), U64::zero(), "{:?}", val);
+        }
+        assert_eq!(parse_string_u256("0xa").unwrap(), U256::from(10_u64));
+        assert_eq!(parse_string_u64("0xa").unwrap(), U64::from(10_u64));
+
+        let max = format!("0x{}", "f".repeat(64));
+        assert_eq!(parse_string_u256(&max).unwrap(), U256::max_value());
+        assert!(parse_string_u256(&format!("0x1{}", "0".repeat(64))).is_err());
+        assert_eq!(
+            parse_string_u64("0xffffffffffffffff").unwrap(),
+            U64::from(u64::MAX)
+        );
+        assert!(parse_string_u64("0x10000000000000000").is_err());
+
+        let got: SU64 = serde_json::from_str("\"0x\"").unwrap();
+        assert_eq!(got, SU64::from(0_u64));
+        let got: SU256 = serde_json::from_str("\"0x0\"").unwrap();
+        assert_eq!(got, SU256::zero());
+    }
+
+    #[test]
+    fn test_deserialize_strict_quantity() {
+        #[derive(Debug, Deserialize)]
+        struct Strict {
+            #[serde(deserialize_with = "deserialize_u256_strict")]
+            big: SU256,
+            #[serde(deserialize_with = "deserialize_u64_strict")]
+            small: SU64,
+        }
+        let parse = |val: &str| {
+            serde_json::from_value::<Strict>(serde_json::json!({"big": val, "small": val}))
+        };
+        let got = parse("0x0").unwrap();
+        assert_eq!((got.big, got.small), (SU256::zero(), SU64::from(0_u64)));
+        let got = parse("0x1").unwrap();
+        assert_eq!(
+            (got.big, got.small),
+            (SU256::from(1_u64), SU64::from(1_u64))
+        );
+        let got = parse("0x1a0").unwrap();
+        assert_eq!(got.small, SU64::from(0x1a0_u64));
+
+        for val in ["0x00", "0x00a", "0x", "", "10"] {
+            assert!(parse(val).is_err(), "{:?}", val);
+        }
+        // the default deserializers stay lenient
+        let got: SU256 = serde_json::from_str("\"0x00a\"").unwrap();
+        assert_eq!(got, SU256::from(10_u64));
+    }
+
+    #[test]
+    fn test_as_number() {
+        #[derive(Debug, PartialEq, Deserialize, Serialize)]
+        struct Numbers {
+            #[serde(with = "su256_as_number")]
+            big: SU256,
+            #[serde(with = "su64_as_number")]
+            small: SU64,
+        }
+        let val = Numbers {
+            big: SU256::from(u64::MAX),
+            small: SU64::from(16_u64),
+        };
+        let json = serde_json::to_value(&val).unwrap();
+        assert_eq!(json, serde_json::json!({"big": u64::MAX, "small": 16}));
+        assert_eq!(serde_json::from_value::<Numbers>(json).unwrap(), val);
+
+        let val = Numbers {
+            big: SU256::from(u64::MAX) + SU256::from(1_u64),
+            small: SU64::from(0_u64),
+        };
+        let json = serde_json::to_value(&val).unwrap();
+        assert_eq!(
+            json,
+            serde_json::json!({"big": "0x10000000000000000", "small": 0})
+        );
+        assert_eq!(serde_json::from_value::<Numbers>(json).unwrap(), val);
+
+        // hex strings are accepted for small values too
+        let got: Numbers =
+            serde_json::from_value(serde_json::json!({"big": "0x10", "small": "0x10"})).unwrap();
+        assert_eq!(
+            (got.big, got.small),
+            (SU256::from(16_u64), SU64::from(16_u64))
+        );
+    }
+
+    #[test]
+    fn test_format_units() {
+        // 1.23456789 ether
+        let value = SU256::from(1_234_567_890_000_000_000_u64);
+        assert_eq!(value.format_units(18, Some(4)), "1.2345");
+        assert_eq!(value.format_units(18, None), "1.23456789");
+        assert_eq!(value.format_units(18, Some(0)), "1");
+        assert_eq!(value.format_ether(), "1.23456789");
+        assert_eq!(value.format_gwei(), "1234567890");
+
+        // trailing zeros left by the truncation are dropped
+        let value = SU256::from(1_500_090_000_000_000_000_u64);
+        assert_eq!(value.format_units(18, Some(4)), "1.5");
+        assert_eq!(value.format_units(18, Some(5)), "1.50009");
+
+        assert_eq!(
+            SU256::from(1_u64).format_units(18, None),
+            "0.000000000000000001"
+        );
+        assert_eq!(SU256::from(1_u64).format_units(18, Some(4)), "0");
+        assert_eq!(SU256::zero().format_ether(), "0");
+        assert_eq!(SU256::from(2_000_000_000_u64).format_gwei(), "2");
+        assert_eq!(SU256::from(42_u64).format_units(0, Some(4)), "42");
+    }
+
+    #[test]
+    fn test_try_parse() {
+        assert_eq!(
+            SU256::from_str_radix("ff", 16).unwrap(),
+            SU256::from(255_u64)
+        );
+        assert_eq!(
+            SU256::from_str_radix("255", 10).unwrap(),
+            SU256::from(255_u64)
+        );
+        assert_eq!(SU256::from_decimal("1000").unwrap(), SU256::from(1000_u64));
+        assert_eq!(SU256::from_hex("3e8").unwrap(), SU256::from(1000_u64));
+        assert_eq!(SU256::from_hex("0x3e8").unwrap(), SU256::from(1000_u64));
+        let hash = "0x33469b22e9f636356c4160a87eb19df52b7412e8eac32a4a55ffe88ea8350788";
+        assert_eq!(SH256::try_from_str(hash), Ok(SH256::from(hash)));
+
+        // invalid input is reported instead of panicking
+        assert!(SU256::from_str_radix("zz", 16).is_err());
+        assert!(SU256::from_decimal("-1").is_err());
+        assert!(SU256::from_decimal("0x10").is_err());
+        assert!(SU256::from_decimal(&"9".repeat(80)).is_err());
+        assert!(SU256::from_hex("0xzz").is_err());
+        assert!(SU256::from_hex(&"f".repeat(65)).is_err());
+        assert!(SH256::try_from_str("0xzz").is_err());
+        assert!(SH256::try_from_str(&format!("{}00", hash)).is_err());
+        assert!(SH160::try_from_str("0x3535353535353535353535353535353535353535ff").is_err());
+        assert!(SH160::try_from_str("not hex").is_err());
+    }
+
+    #[test]
+    fn test_hash_map_keys() {
+        use std::collections::{HashMap, HashSet};
+        let alice: SH160 = "0x3535353535353535353535353535353535353535".into();
+        let bob: SH160 = "0x9d8a62f656a8d1615c1294fd71e9cfb3e4855a4f".into();
+        let mut balances: HashMap<SH160, u64> = HashMap::new();
+        balances.insert(alice, 1);
+        balances.insert(bob, 2);
+        *balances.entry(alice).or_default() += 10;
+        assert_eq!(balances.len(), 2);
+        assert_eq!(balances.get(&alice), Some(&11));
+        // equal values parsed differently land on the same key
+        assert_eq!(
+            balances.get(&SH160::from("0x3535353535353535353535353535353535353535")),
+            Some(&11)
+        );
+        assert_eq!(balances.get(&SH160::zero()), None);
+
+        let mut seen: HashSet<SH256> = HashSet::new();
+        assert!(seen.insert(SH256::from([1_u8; 32])));
+        assert!(seen.insert(SH256::zero()));
+        assert!(!seen.insert(SH256::from([1_u8; 32])));
+        assert!(!seen.insert(SH256::default()));
+        assert_eq!(seen.len(), 2);
+
+        let values: HashSet<SU256> = [SU256::from(10_u64), SU256::from("0x0a"), SU256::zero()]
+            .iter()
+            .copied()
+            .collect();
+        assert_eq!(values.len(), 2);
+    }
+}
@@ -1,9 +1,11 @@
 use std::prelude::v1::*;
 
-use super::{SH160, SH256, SU256, SU64};
+use super::{create_address, Bloom, Signer, TransactionInner, SH160, SH256, SU256, SU64};
+use crypto::keccak_hash;
 use hex::HexBytes;
 use rlp_derive::RlpEncodable;
 use serde::{Deserialize, Serialize, de::DeserializeOwned};
+use std::sync::Arc;
 
 #[derive(Default, Clone, Debug, Deserialize, Serialize, PartialEq, Eq)]
 #[serde(rename_all = "camelCase")]
@@ -29,10 +31,16 @@ pub struct Receipt {
     pub transaction_index: SU64,     // uint        `:""`
 }
 
+// Breaking: `logs`, `logs_bloom` and `rlp_bytes` are required, existing
+// implementors must add them. They feed the block bloom and the receipts
+// root, where a default (e.g. an empty bloom) would be silently wrong.
 pub trait ReceiptTrait: Clone + DeserializeOwned {
     fn status(&self) -> SU64;
     fn gas_used(&self) -> SU64;
     fn transaction_hash(&self) -> &SH256;
+    fn logs(&self) -> &[Log];
+    fn logs_bloom(&self) -> &HexBytes;
+    fn rlp_bytes(&self) -> Vec<u8>;
 }
 
 impl ReceiptTrait for Receipt {
@@ -45,9 +53,149 @@ impl ReceiptTrait for Receipt {
     fn transaction_hash(&self) -> &SH256 {
         &self.transaction_hash
     }
+    fn logs(&self) -> &[Log] {
+        &self.logs
+    }
+    fn logs_bloom(&self) -> &HexBytes {
+        &self.logs_bloom
+    }
+    fn rlp_bytes(&self) -> Vec<u8> {
+        Receipt::rlp_bytes(self)
+    }
 }
 
 impl Receipt {
+    pub fn sort_by_index(receipts: &mut [Receipt]) {
+        receipts.sort_by_key(|receipt| receipt.transaction_index);
+    }
+
+    pub fn effective_gas_price(&self, tx: &TransactionInner, base_fee: Option<SU256>) -> SU256 {
+        tx.gas_price(base_fee)
+    }
+
+    // gas_used and the fees come from untrusted receipts and txs.
+    pub fn fee_paid(
+        &self,
+        tx: &TransactionInner,
+        base_fee: Option<SU256>,
+    ) -> Result<SU256, String> {
+        let gas_used: SU256 = self.gas_used.into();
+        let gas_price = self.effective_gas_price(tx, base_fee);
+        let fee = gas_used
+            .raw()
+            .checked_mul(*gas_price.raw())
+            .ok_or_else(|| {
+                format!(
+                    "gas_used * gas_price overflow: {} * {}",
+                    gas_used, gas_price
+                )
+            })?;
+        Ok(fee.into())
+    }
+
+    // The cumulative gas of a receipt is its own gas on top of the previous one's.
+    pub fn validate_cumulative(&self, prev: &Receipt) -> bool {
+        let expect = prev
+            .cumulative_gas_used
+            .as_u64()
+            .checked_add(self.gas_used.as_u64());
+        expect == Some(self.cumulative_gas_used.as_u64())
+    }
+
+    pub fn fill_gas_used_from_cumulative(receipts: &mut [Receipt]) -> Result<(), String> {
+        let mut prev = 0_u64;
+        for (idx, receipt) in receipts.iter_mut().enumerate() {
+            let cumulative = receipt.cumulative_gas_used.as_u64();
+            if cumulative < prev {
+                return Err(format!(
+                    "[receipt.{}] cumulative gas decreased: {} -> {}",
+                    idx, prev, cumulative
+                ));
+            }
+            receipt.gas_used = (cumulative - prev).into();
+            prev = cumulative;
+        }
+        Ok(())
+    }
+
+    pub fn fill_cumulative_from_gas_used(receipts: &mut [Receipt]) {
+        let mut cumulative_gas_used = SU64::default();
+        for receipt in receipts {
+            cumulative_gas_used += receipt.gas_used;
+            receipt.cumulative_gas_used = cumulative_gas_used;
+        }
+    }
+
+    pub fn finalize_block(receipts: &mut [Receipt]) {
+        Self::fill_cumulative_from_gas_used(receipts);
+        Self::index_logs(receipts);
+        for receipt in receipts {
+            let mut bloom = Bloom::new();
+            bloom.add_logs(&receipt.logs);
+            receipt.logs_bloom = bloom.to_hex();
+        }
+    }
+
+    // Fills the inclusion info of every receipt and log. The log index runs
+    // across the whole block rather than restarting for each receipt.
+    pub fn assign_log_indices(receipts: &mut [Receipt], block_number: SU64, block_hash: SH256) {
+        Self::index_logs(receipts);
+        for receipt in receipts {
+            receipt.block_number = Some(block_number.into());
+            receipt.block_hash = Some(block_hash);
+            for log in &mut receipt.logs {
+                log.block_number = block_number;
+                log.block_hash = block_hash;
+            }
+        }
+    }
+
+    // Numbers the receipts by their position and their logs across the block.
+    fn index_logs(receipts: &mut [Receipt]) {
+        let mut log_index = 0_u64;
+        for (idx, receipt) in receipts.iter_mut().enumerate() {
+            receipt.transaction_index = (idx as u64).into();
+            for log in &mut receipt.logs {
+                log.transaction_hash = receipt.transaction_hash;
+                log.transaction_index = receipt.transaction_index;
+                log.log_index = log_index.into();
+                log_index += 1;
+            }
+        }
+    }
+
+    pub fn set_contract_address(&mut self, sender: &SH160, nonce: u64) {
+        self.contract_address = Some(create_address(sender, nonce));
+    }
+
+    pub fn fill_contract_addresses(
+        receipts: &mut [Receipt],
+        txs: &[Arc<TransactionInner>],
+        signer: &Signer,
+    ) -> Result<(), String> {
+        if receipts.len() != txs.len() {
+            return Err(format!(
+                "receipts and txs length mismatch: {} != {}",
+                receipts.len(),
+                txs.len()
+            ));
+        }
+        // recover every creator first so a bad tx leaves the receipts untouched
+        let mut creators = Vec::new();
+        for (idx, tx) in txs.iter().enumerate() {
+            if tx.to().is_none() {
+                let sender = signer
+                    .try_sender(tx)
+                    .map_err(|err| format!("recover sender of tx[{}] failed: {:?}", idx, err))?;
+                creators.push((idx, sender, tx.nonce()));
+            }
+        }
+        for (idx, sender, nonce) in creators {
+            receipts[idx].set_contract_address(&sender, nonce);
+        }
+        Ok(())
+    }
+
     pub fn status_encoding(&self) -> HexBytes {
         match &self.root {
             Some(n) => n.clone(),
@@ -62,6 +210,15 @@ impl Receipt {
         }
     }
 
+    // Inverse of `status_encoding`. A 32-byte post-state root (pre-Byzantium)
+    // carries no status, which is reported as 0 like geth does.
+    pub fn status_from_encoding(encoding: &[u8]) -> SU64 {
+        match encoding {
+            [1] => SU64::from(1),
+            _ => SU64::from(0),
+        }
+    }
+
     pub fn succ(&self) -> bool {
         self.status.as_u64() == 1
     }
@@ -135,6 +292,44 @@ pub struct Log {
     pub removed: bool,
 }
 
+impl Log {
+    pub fn event_signature(&self) -> Option<&SH256> {
+        self.topics.first()
+    }
+
+    pub fn as_erc20_transfer(&self) -> Option<(SH160, SH160, SU256)> {
+        // ERC-721 shares the signature but indexes the token id as a 4th topic
+        if self.topics.len() != 3 || self.data.len() != 32 {
+            return None;
+        }
+        if self.topics[0] != event_topic("Transfer(address,address,uint256)") {
+            return None;
+        }
+        let from = self.topics[1].as_address();
+        let to = self.topics[2].as_address();
+        let value = SU256::from_big_endian(&self.data);
+        Some((from, to, value))
+    }
+
+    pub fn topic_as_address(&self, i: usize) -> Option<SH160> {
+        self.topics.get(i).map(|topic| topic.as_address())
+    }
+
+    // Reverted by a chain reorganisation, see `removed`.
+    pub fn is_reverted(&self) -> bool {
+        self.removed
+    }
+}
+
+pub fn event_topic(signature: &str) -> SH256 {
+    keccak_hash(signature.as_bytes()).into()
+}
+
+// Splits logs into (active, removed by a reorg).
+pub fn partition_removed(logs: &[Log]) -> (Vec<&Log>, Vec<&Log>) {
+    logs.iter().partition(|log| !log.is_reverted())
+}
+
 impl rlp::Encodable for Log {
     fn rlp_append(&self, s: &mut rlp::RlpStream) {
         #[derive(RlpEncodable, Serialize)]
@@ -152,3 +347,433 @@ impl rlp::Encodable for Log {
         .rlp_append(s)
     }
 }
+
+pub fn deserialize_logs_lenient(json: &str) -> (Vec<Log>, Vec<String>) {
+    let items: Vec<serde_json::Value> = match serde_json::from_str(json) {
+        Ok(items) => items,
+        Err(err) => return (Vec::new(), vec![format!("invalid logs: {:?}", err)]),
+    };
+    let mut logs = Vec::with_capacity(items.len());
+    let mut errors = Vec::new();
+    for (idx, item) in items.into_iter().enumerate() {
+        match serde_json::from_value(item) {
+            Ok(log) => logs.push(log),
+            Err(err) => errors.push(format!("[{}] {:?}", idx, err)),
+        }
+    }
+    (logs, errors)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tx::tests::{decode_tx, signer, CREATE_TX, DYNAMIC_FEE_TX, LEGACY_TX};
+
+    #[test]
+    fn test_fill_contract_addresses() {
+        let txs = vec![
+            Arc::new(decode_tx(LEGACY_TX)),
+            Arc::new(decode_tx(CREATE_TX)),
+        ];
+        let mut receipts = vec![Receipt::default(); 2];
+        Receipt::fill_contract_addresses(&mut receipts, &txs, &signer()).unwrap();
+        assert_eq!(receipts[0].contract_address, None);
+        assert_eq!(
+            receipts[1].contract_address,
+            Some("0x34b544d335dc19a7cbef592c72e74c9745e603bd".into())
+        );
+
+        let mut receipts = vec![Receipt::default(); 1];
+        assert!(Receipt::fill_contract_addresses(&mut receipts, &txs, &signer()).is_err());
+    }
+
+    #[test]
+    fn test_fill_contract_addresses_bad_signature() {
+        let mut tx = decode_tx(CREATE_TX);
+        if let TransactionInner::Legacy(tx) = &mut tx {
+            tx.v = 29_u64.into();
+        }
+        let txs = vec![Arc::new(decode_tx(CREATE_TX)), Arc::new(tx)];
+        let mut receipts = vec![Receipt::default(); 2];
+        assert!(Receipt::fill_contract_addresses(&mut receipts, &txs, &signer()).is_err());
+        assert_eq!(receipts, vec![Receipt::default(); 2]);
+    }
+
+    fn log_json() -> serde_json::Value {
+        serde_json::json!({
+            "address": "0x3535353535353535353535353535353535353535",
+            "topics": [],
+            "data": "0x",
+            "blockNumber": "0x1",
+            "transactionHash": "0x0000000000000000000000000000000000000000000000000000000000000000",
+            "transactionIndex": "0x0",
+            "blockHash": "0x0000000000000000000000000000000000000000000000000000000000000000",
+            "logIndex": "0x0",
+            "removed": false,
+        })
+    }
+
+    #[derive(Clone, Deserialize)]
+    struct StatusReceipt {
+        status: SU64,
+        transaction_hash: SH256,
+        logs_bloom: HexBytes,
+    }
+
+    impl ReceiptTrait for StatusReceipt {
+        fn status(&self) -> SU64 {
+            self.status
+        }
+        fn gas_used(&self) -> SU64 {
+            SU64::default()
+        }
+        fn transaction_hash(&self) -> &SH256 {
+            &self.transaction_hash
+        }
+        fn logs(&self) -> &[Log] {
+            &[]
+        }
+        fn logs_bloom(&self) -> &HexBytes {
+            &self.logs_bloom
+        }
+        fn rlp_bytes(&self) -> Vec<u8> {
+            rlp::encode(&self.status).to_vec()
+        }
+    }
+
+    fn logs_and_bloom<R: ReceiptTrait>(receipt: &R) -> (usize, HexBytes, Vec<u8>) {
+        (
+            receipt.logs().len(),
+            receipt.logs_bloom().clone(),
+            receipt.rlp_bytes(),
+        )
+    }
+
+    #[test]
+    fn test_receipt_trait() {
+        let mut bloom = Bloom::new();
+        let log: Log = serde_json::from_value(log_json()).unwrap();
+        bloom.add_logs(std::slice::from_ref(&log));
+        let receipt = Receipt {
+            status: 1_u64.into(),
+            logs: vec![log],
+            logs_bloom: bloom.to_hex(),
+            ..Default::default()
+        };
+        assert_eq!(
+            logs_and_bloom(&receipt),
+            (1, bloom.to_hex(), receipt.rlp_bytes())
+        );
+
+        let receipt = StatusReceipt {
+            status: 1_u64.into(),
+            transaction_hash: SH256::default(),
+            logs_bloom: Bloom::new().to_hex(),
+        };
+        assert_eq!(
+            logs_and_bloom(&receipt),
+            (0, Bloom::new().to_hex(), vec![0x01])
+        );
+    }
+
+    #[test]
+    fn test_deserialize_logs_lenient() {
+        let mut bad = log_json();
+        bad["address"] = "0x12zz".into();
+        let mut second = log_json();
+        second["logIndex"] = "0x1".into();
+        let json = serde_json::to_string(&vec![log_json(), bad, second]).unwrap();
+
+        let (logs, errors) = deserialize_logs_lenient(&json);
+        assert_eq!(logs.len(), 2);
+        assert_eq!(logs[1].log_index, SU64::from(1_u64));
+        assert_eq!(errors.len(), 1);
+        assert!(errors[0].starts_with("[1]"), "{}", errors[0]);
+
+        let (logs, errors) = deserialize_logs_lenient("{}");
+        assert!(logs.is_empty());
+        assert_eq!(errors.len(), 1);
+    }
+
+    #[test]
+    fn test_sort_by_index() {
+        let mut receipts: Vec<Receipt> = [3_u64, 0, 2, 1]
+            .iter()
+            .map(|idx| Receipt {
+                transaction_index: (*idx).into(),
+                ..Default::default()
+            })
+            .collect();
+        Receipt::sort_by_index(&mut receipts);
+        let indices: Vec<u64> = receipts
+            .iter()
+            .map(|r| r.transaction_index.as_u64())
+            .collect();
+        assert_eq!(indices, vec![0, 1, 2, 3]);
+    }
+
+    #[test]
+    fn test_finalize_block() {
+        let log: Log = serde_json::from_value(log_json()).unwrap();
+        let receipt = |gas: u64, logs: usize, hash: u8| Receipt {
+            gas_used: gas.into(),
+            transaction_hash: SH256::from([hash; 32]),
+            logs: vec![log.clone(); logs],
+            ..Default::default()
+        };
+        let mut receipts = vec![
+            receipt(21000, 1, 1),
+            receipt(50000, 0, 2),
+            receipt(30000, 2, 3),
+        ];
+        Receipt::finalize_block(&mut receipts);
+
+        let cumulative: Vec<u64> = receipts
+            .iter()
+            .map(|r| r.cumulative_gas_used.as_u64())
+            .collect();
+        assert_eq!(cumulative, vec![21000, 71000, 101000]);
+        let mut log_index = 0;
+        for (idx, receipt) in receipts.iter().enumerate() {
+            assert_eq!(receipt.transaction_index.as_u64(), idx as u64);
+            for log in &receipt.logs {
+                assert_eq!(log.log_index.as_u64(), log_index);
+                assert_eq!(log.transaction_index, receipt.transaction_index);
+                assert_eq!(log.transaction_hash, receipt.transaction_hash);
+                log_index += 1;
+            }
+            let mut bloom = Bloom::new();
+            bloom.add_logs(&receipt.logs);
+            assert_eq!(receipt.logs_bloom, bloom.to_hex());
+        }
+        assert_eq!(log_index, 3);
+        assert_eq!(receipts[1].logs_bloom, Bloom::new().to_hex());
+    }
+
+    #[test]
+    fn test_event_topic() {
+        let transfer: SH256 =
+            "0xddf252ad1be2c89b69c2b068fc378daa952ba7f163c4a11628f55a4df523b3ef".into();
+        assert_eq!(event_topic("Transfer(address,address,uint256)"), transfer);
+        assert_eq!(
+            event_topic("Approval(address,address,uint256)"),
+            "0x8c5be1e5ebec7d5bd14f71427d1e84f3dd0314c0f7b2291e5b200ac8c7c3b925".into()
+        );
+
+        let mut log: Log = serde_json::from_value(log_json()).unwrap();
+        assert_eq!(log.event_signature(), None);
+        log.topics.push(transfer);
+        assert_eq!(log.event_signature(), Some(&transfer));
+    }
+
+    #[test]
+    fn test_as_erc20_transfer() {
+        let from: SH160 = "0x9d8a62f656a8d1615c1294fd71e9cfb3e4855a4f".into();
+        let to: SH160 = "0x3535353535353535353535353535353535353535".into();
+        let topic = |addr: &SH160| {
+            let mut topic = [0_u8; 32];
+            topic[12..].copy_from_slice(addr.as_bytes());
+            SH256::from(topic)
+        };
+        let mut log: Log = serde_json::from_value(log_json()).unwrap();
+        log.topics = vec![
+            event_topic("Transfer(address,address,uint256)"),
+            topic(&from),
+            topic(&to),
+        ];
+        log.data = hex::decode("00000000000000000000000000000000000000000000000000000000000f4240")
+            .unwrap()
+            .into();
+        assert_eq!(
+            log.as_erc20_transfer(),
+            Some((from, to, SU256::from(1_000_000_u64)))
+        );
+
+        let mut approval = log.clone();
+        approval.topics[0] = event_topic("Approval(address,address,uint256)");
+        assert_eq!(approval.as_erc20_transfer(), None);
+
+        // ERC-721 indexes the token id instead
+        let mut nft = log.clone();
+        nft.topics.push(SH256::from([1_u8; 32]));
+        nft.data = HexBytes::new();
+        assert_eq!(nft.as_erc20_transfer(), None);
+
+        let mut short = log;
+        short.data = HexBytes::from(&[1_u8; 31][..]);
+        assert_eq!(short.as_erc20_transfer(), None);
+    }
+
+    #[test]
+    fn test_topic_as_address() {
+        let to: SH160 = "0x3535353535353535353535353535353535353535".into();
+        let mut log: Log = serde_json::from_value(log_json()).unwrap();
+        log.topics = vec![
+            event_topic("Transfer(address,address,uint256)"),
+            "0x0000000000000000000000009d8a62f656a8d1615c1294fd71e9cfb3e4855a4f".into(),
+            "0x0000000000000000000000003535353535353535353535353535353535353535".into(),
+        ];
+        assert_eq!(log.topic_as_address(2), Some(to));
+        assert_eq!(log.topics[2].as_address(), to);
+        assert_eq!(
+            log.topic_as_address(1),
+            Some("0x9d8a62f656a8d1615c1294fd71e9cfb3e4855a4f".into())
+        );
+        assert_eq!(log.topic_as_address(3), None);
+    }
+
+    #[test]
+    fn test_fee_paid() {
+        let gwei = |n: u64| SU256::from(n * 1_000_000_000);
+        // tip of 2 gwei, max fee of 100 gwei
+        let tx = decode_tx(DYNAMIC_FEE_TX);
+        let receipt = Receipt {
+            gas_used: 21000_u64.into(),
+            cumulative_gas_used: 50000_u64.into(),
+            ..Default::default()
+        };
+        assert_eq!(receipt.effective_gas_price(&tx, Some(gwei(30))), gwei(32));
+        assert_eq!(receipt.fee_paid(&tx, Some(gwei(30))), Ok(gwei(32 * 21000)));
+        assert_eq!(receipt.effective_gas_price(&tx, Some(gwei(99))), gwei(100));
+        assert_eq!(receipt.fee_paid(&tx, Some(gwei(99))), Ok(gwei(100 * 21000)));
+
+        let mut expensive = tx.clone();
+        if let TransactionInner::DynamicFee(tx) = &mut expensive {
+            tx.max_fee_per_gas = crate::U256::max_value().into();
+            tx.max_priority_fee_per_gas = crate::U256::max_value().into();
+        }
+        // base fee + tip overflows and falls back to the fee cap
+        assert_eq!(
+            receipt.effective_gas_price(&expensive, Some(gwei(30))),
+            SU256::from(crate::U256::max_value())
+        );
+        assert!(receipt.fee_paid(&expensive, Some(gwei(30))).is_err());
+        assert_eq!(
+            Receipt::default().fee_paid(&expensive, None),
+            Ok(SU256::zero())
+        );
+
+        let prev = Receipt {
+            cumulative_gas_used: 29000_u64.into(),
+            ..Default::default()
+        };
+        assert!(receipt.validate_cumulative(&prev));
+        assert!(!receipt.validate_cumulative(&receipt));
+        assert!(!receipt.validate_cumulative(&Receipt::default()));
+    }
+
+    #[test]
+    fn test_fill_gas_used() {
+        let receipts_with = |cumulative: &[u64]| -> Vec<Receipt> {
+            cumulative
+                .iter()
+                .map(|gas| Receipt {
+                    cumulative_gas_used: (*gas).into(),
+                    ..Default::default()
+                })
+                .collect()
+        };
+        let mut receipts = receipts_with(&[21000, 71000, 101000]);
+        Receipt::fill_gas_used_from_cumulative(&mut receipts).unwrap();
+        let gas_used: Vec<u64> = receipts.iter().map(|r| r.gas_used.as_u64()).collect();
+        assert_eq!(gas_used, vec![21000, 50000, 30000]);
+
+        for receipt in &mut receipts {
+            receipt.cumulative_gas_used = SU64::default();
+        }
+        Receipt::fill_cumulative_from_gas_used(&mut receipts);
+        let cumulative: Vec<u64> = receipts
+            .iter()
+            .map(|r| r.cumulative_gas_used.as_u64())
+            .collect();
+        assert_eq!(cumulative, vec![21000, 71000, 101000]);
+        for i in 1..receipts.len() {
+            assert!(receipts[i].validate_cumulative(&receipts[i - 1]));
+        }
+
+        let mut receipts = receipts_with(&[21000, 71000, 50000]);
+        assert_eq!(
+            Receipt::fill_gas_used_from_cumulative(&mut receipts),
+            Err("[receipt.2] cumulative gas decreased: 71000 -> 50000".into())
+        );
+    }
+
+    #[test]
+    fn test_status_from_encoding() {
+        for status in [0_u64, 1] {
+            let receipt = Receipt {
+                status: status.into(),
+                ..Default::default()
+            };
+            let encoding = receipt.status_encoding();
+            assert_eq!(Receipt::status_from_encoding(&encoding), SU64::from(status));
+        }
+        assert_eq!(Receipt::status_from_encoding(&[]), SU64::from(0));
+        assert_eq!(Receipt::status_from_encoding(&[1]), SU64::from(1));
+
+        // pre-Byzantium receipts carry the post-state root instead
+        let receipt = Receipt {
+            root: Some(HexBytes::from(&[1_u8; 32][..])),
+            status: 1_u64.into(),
+            ..Default::default()
+        };
+        assert_eq!(receipt.status_encoding().len(), 32);
+        assert_eq!(
+            Receipt::status_from_encoding(&receipt.status_encoding()),
+            SU64::from(0)
+        );
+    }
+
+    #[test]
+    fn test_assign_log_indices() {
+        let log: Log = serde_json::from_value(log_json()).unwrap();
+        let mut receipts = vec![
+            Receipt {
+                transaction_hash: SH256::from([1_u8; 32]),
+                logs: vec![log.clone()],
+                ..Default::default()
+            },
+            Receipt {
+                transaction_hash: SH256::from([2_u8; 32]),
+                logs: vec![log.clone(), log],
+                ..Default::default()
+            },
+        ];
+        let block_hash = SH256::from([0xbb_u8; 32]);
+        Receipt::assign_log_indices(&mut receipts, 16_u64.into(), block_hash);
+
+        let indices: Vec<u64> = receipts
+            .iter()
+            .flat_map(|r| r.logs.iter().map(|log| log.log_index.as_u64()))
+            .collect();
+        assert_eq!(indices, vec![0, 1, 2]);
+        for (idx, receipt) in receipts.iter().enumerate() {
+            assert_eq!(receipt.transaction_index.as_u64(), idx as u64);
+            assert_eq!(receipt.block_hash, Some(block_hash));
+            for log in &receipt.logs {
+                assert_eq!(log.block_number, SU64::from(16_u64));
+                assert_eq!(log.block_hash, block_hash);
+                assert_eq!(log.transaction_hash, receipt.transaction_hash);
+                assert_eq!(log.transaction_index, receipt.transaction_index);
+            }
+        }
+    }
+
+    #[test]
+    fn test_partition_removed() {
+        let active: Log = serde_json::from_value(log_json()).unwrap();
+        let mut removed = active.clone();
+        removed.removed = true;
+        removed.log_index = 1_u64.into();
+        assert!(!active.is_reverted());
+        assert!(removed.is_reverted());
+
+        let logs = vec![active.clone(), removed.clone(), active.clone()];
+        let (got_active, got_removed) = partition_removed(&logs);
+        assert_eq!(got_active, vec![&active, &active]);
+        assert_eq!(got_removed, vec![&removed]);
+
+        let (got_active, got_removed) = partition_removed(&[]);
+        assert!(got_active.is_empty() && got_removed.is_empty());
+    }
+}
@@ -8,19 +8,42 @@ use serde::{Deserialize, Serialize};
 #[derive(Clone)]
 pub struct TimeBasedSigner {
     prvkey: Secp256k1PrivateKey,
+    domain: String,
 }
 
 impl TimeBasedSigner {
     pub fn new(prvkey: Secp256k1PrivateKey) -> Self {
-        Self { prvkey }
+        Self::with_domain(prvkey, "")
     }
+
+    pub fn with_domain(prvkey: Secp256k1PrivateKey, domain: &str) -> Self {
+        Self {
+            prvkey,
+            domain: domain.into(),
+        }
+    }
+
+    pub fn domain(&self) -> &str {
+        &self.domain
+    }
+
     pub fn sign<T: Serialize>(&self, ts: u64, t: T) -> TimeBasedSignature<T> {
-        let msg = serde_json::to_vec(&(&t, ts)).unwrap();
+        let msg = signing_msg(&self.domain, &t, ts);
         let sig = self.prvkey.sign(&msg).to_array();
         TimeBasedSignature(t, ts, HexBytes::from(&sig[..]))
     }
 }
 
+// an empty domain keeps the original `(t, ts)` preimage so that
+// signatures produced before domains were introduced still verify.
+fn signing_msg<T: Serialize>(domain: &str, t: &T, ts: u64) -> Vec<u8> {
+    if domain.is_empty() {
+        serde_json::to_vec(&(t, ts)).unwrap()
+    } else {
+        serde_json::to_vec(&(domain, t, ts)).unwrap()
+    }
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct TimeBasedSignature<T>(T, u64, HexBytes);
 
@@ -36,13 +59,17 @@ impl<T> TimeBasedSignature<T> {
 
 impl<T: Serialize> TimeBasedSignature<T> {
     pub fn recover(&self) -> Result<SH256, String> {
+        self.recover_with_domain("")
+    }
+
+    pub fn recover_with_domain(&self, domain: &str) -> Result<SH256, String> {
         if self.2.len() != 65 {
             return Err(format!("invalid signature"));
         }
         let mut sig = [0_u8; 65];
         sig.copy_from_slice(&self.2);
 
-        let msg = keccak_hash(&serde_json::to_vec(&(&self.0, self.1)).unwrap());
+        let msg = keccak_hash(&signing_msg(domain, &self.0, self.1));
 
         let pubkey = match crypto::secp256k1_ecdsa_recover(&sig, &msg) {
             Some(signer) => signer,
@@ -50,4 +77,49 @@ impl<T: Serialize> TimeBasedSignature<T> {
         };
         Ok(keccak_hash(&pubkey).into())
     }
+
+    pub fn verify(&self, domain: &str, signer: &SH256) -> bool {
+        match self.recover_with_domain(domain) {
+            Ok(recovered) => &recovered == signer,
+            Err(_) => false,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // signed by the key 0x4646..46
+    const SIGNER: &str = "0x9cead80a9d0a0951cdbf966b9d8a62f656a8d1615c1294fd71e9cfb3e4855a4f";
+
+    fn signature(sig: &str) -> TimeBasedSignature<String> {
+        TimeBasedSignature("hello".into(), 1000, hex::decode(sig).unwrap().into())
+    }
+
+    #[test]
+    fn test_domain_separation() {
+        let signer: SH256 = SIGNER.into();
+        let sig = signature("d80f4aa7cef7b4ab91978efa947e0d623b689045a1915aa01cbaf103d3fd6a3a04074817eae5e572e4e6769b0cc81ca9f5c22f3c62e7b35f4febb12dae44bf0801");
+        assert_eq!(sig.recover_with_domain("A"), Ok(signer));
+        assert!(sig.verify("A", &signer));
+        assert_ne!(sig.recover_with_domain("B"), Ok(signer));
+        assert!(!sig.verify("B", &signer));
+        assert!(!sig.verify("", &signer));
+    }
+
+    #[test]
+    fn test_empty_domain_compat() {
+        let signer: SH256 = SIGNER.into();
+        // signed over the pre-domain `(t, ts)` preimage
+        let sig = signature("8bb801fcd76ed934b931c5b1146f0411a74d3d2d1c513b3502bebd1e6924f91774390f835bb6655893ff3f53eb84ad60df05fe3bf5651488ea5cc59ca394c92b01");
+        assert_eq!(sig.recover(), Ok(signer));
+        assert!(sig.verify("", &signer));
+        assert!(!sig.verify("A", &signer));
+        assert_eq!(sig.data(), "hello");
+        assert_eq!(sig.time(), 1000);
+
+        let short = TimeBasedSignature("hello".to_string(), 1000, HexBytes::from(&[0_u8; 64][..]));
+        assert!(short.recover().is_err());
+    }
 }
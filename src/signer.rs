@@ -1,40 +1,92 @@
 use std::prelude::v1::*;
 
-use super::{TransactionInner, SH160, SU256};
-use crypto::{secp256k1_recover_pubkey, Secp256k1PrivateKey};
+use super::{ChainId, TransactionInner, SH160, SH256, SU256};
+use crypto::{keccak_hash, Secp256k1PrivateKey};
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum SignerError {
+    ChainIdMismatch { expect: SU256, got: SU256 },
+    InvalidSignature,
+}
 
 #[derive(Clone, Copy, Debug)]
 pub struct Signer {
     pub chain_id: SU256,
+    // some tools emit typed txs with chain id 0 as a wildcard
+    pub allow_zero_chain_id: bool,
 }
 
 impl Signer {
     pub fn new(chain_id: SU256) -> Self {
-        Self { chain_id }
+        Self {
+            chain_id,
+            allow_zero_chain_id: false,
+        }
+    }
+
+    pub fn with_chain_id(chain_id: ChainId) -> Self {
+        Self::new(chain_id.as_u256())
+    }
+
+    pub fn with_allow_zero_chain_id(mut self, allow: bool) -> Self {
+        self.allow_zero_chain_id = allow;
+        self
     }
 
     pub fn sender(&self, inner: &TransactionInner) -> SH160 {
-        let sig = inner.signature(self.chain_id.as_u64());
-        match inner {
-            TransactionInner::DynamicFee(tx) => {
-                if tx.chain_id != self.chain_id {
-                    panic!(
-                        "chain id not match, expect: {}, got: {}",
-                        self.chain_id, tx.chain_id
-                    );
-                }
+        match self.try_sender(inner) {
+            Ok(sender) => sender,
+            Err(SignerError::ChainIdMismatch { expect, got }) => {
+                panic!("chain id not match, expect: {}, got: {}", expect, got)
             }
-            TransactionInner::AccessList(tx) => {
-                if tx.chain_id != self.chain_id {
-                    panic!("chain id not match");
-                }
+            Err(err) => panic!("recover sender failed: {:?}", err),
+        }
+    }
+
+    pub fn try_sender(&self, inner: &TransactionInner) -> Result<SH160, SignerError> {
+        Ok(self.sender_and_hash(inner)?.0)
+    }
+
+    // Recovers the sender and returns the signing hash it was recovered from,
+    // so callers needing both only build the preimage once.
+    pub fn sender_and_hash(&self, inner: &TransactionInner) -> Result<(SH160, SH256), SignerError> {
+        let (chain_id, y_parity) = match inner {
+            TransactionInner::DynamicFee(tx) => (Some(tx.chain_id), Some(&tx.v)),
+            TransactionInner::AccessList(tx) => (Some(tx.chain_id), Some(&tx.v)),
+            TransactionInner::Legacy(tx) => (legacy_chain_id(&tx.v)?, None),
+        };
+        // typed txs carry the bare parity, accepting 27/28 as well would give
+        // the same signature a second encoding with a different tx hash
+        if let Some(y_parity) = y_parity {
+            if y_parity > &SU256::one() {
+                return Err(SignerError::InvalidSignature);
+            }
+        }
+        if let Some(chain_id) = chain_id {
+            let typed = y_parity.is_some();
+            let wildcard = typed && self.allow_zero_chain_id && chain_id.is_zero();
+            if chain_id != self.chain_id && !wildcard {
+                return Err(SignerError::ChainIdMismatch {
+                    expect: self.chain_id,
+                    got: chain_id,
+                });
             }
-            TransactionInner::Legacy(_) => {}
         }
 
-        let msg = self.msg(inner);
-        let pubkey = secp256k1_recover_pubkey(&sig, &msg[..]);
-        pubkey.eth_accountid().into()
+        let sig = inner.signature(self.chain_id.as_u64());
+        let mut sig_bytes = [0_u8; 65];
+        sig_bytes[..32].copy_from_slice(&sig.r);
+        sig_bytes[32..64].copy_from_slice(&sig.s);
+        sig_bytes[64] = sig.v;
+
+        let hash = self.signing_hash(inner);
+        let sender = ecrecover(&hash, &sig_bytes).ok_or(SignerError::InvalidSignature)?;
+        Ok((sender, hash))
+    }
+
+    // None if the configured chain id does not fit in a u64.
+    pub fn chain_id(&self) -> Option<ChainId> {
+        core::convert::TryFrom::try_from(self.chain_id).ok()
     }
 
     pub fn sign(&self, tx: &mut TransactionInner, key: &Secp256k1PrivateKey) {
@@ -44,4 +96,185 @@ impl Signer {
     pub fn msg(&self, tx: &TransactionInner) -> Vec<u8> {
         tx.sign_msg(&self.chain_id)
     }
+
+    pub fn signing_hash(&self, tx: &TransactionInner) -> SH256 {
+        tx.signing_hash(&self.chain_id)
+    }
+}
+
+// The chain id an EIP-155 protected legacy tx commits to through its `v`.
+pub fn legacy_chain_id(v: &SU256) -> Result<Option<SU256>, SignerError> {
+    if v.raw().bits() > 64 {
+        return Err(SignerError::InvalidSignature);
+    }
+    match v.as_u64() {
+        0 | 1 | 27 | 28 => Ok(None),
+        v if v >= 35 => Ok(Some(((v - 35) / 2).into())),
+        _ => Err(SignerError::InvalidSignature),
+    }
+}
+
+// Recovers an EIP-191/EIP-712 signature, accepting both the raw recovery id
+// and the 27/28 form used by wallets.
+pub(crate) fn recover_address(hash: &SH256, sig: &[u8; 65]) -> Option<SH160> {
+    let mut sig = *sig;
+    if sig[64] >= 27 {
+        sig[64] -= 27;
+    }
+    ecrecover(hash, &sig)
+}
+
+// `sig[64]` must be the raw recovery id.
+fn ecrecover(hash: &SH256, sig: &[u8; 65]) -> Option<SH160> {
+    if sig[64] > 1 {
+        return None;
+    }
+    let pubkey = crypto::secp256k1_ecdsa_recover(sig, &hash.raw().0)?;
+    let hash = keccak_hash(&pubkey);
+    Some(SH160::from_slice(&hash[12..]))
+}
+
+pub fn hash_personal_message(msg: &[u8]) -> SH256 {
+    let prefix = format!("\x19Ethereum Signed Message:\n{}", msg.len());
+    let mut data = Vec::with_capacity(prefix.len() + msg.len());
+    data.extend_from_slice(prefix.as_bytes());
+    data.extend_from_slice(msg);
+    keccak_hash(&data).into()
+}
+
+pub fn recover_personal_signature(msg: &[u8], sig: &[u8; 65]) -> Result<SH160, String> {
+    let hash = hash_personal_message(msg);
+    recover_address(&hash, sig).ok_or_else(|| format!("invalid signature"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tx::tests::{decode_tx, signer, ACCESS_LIST_TX, DYNAMIC_FEE_TX, LEGACY_TX, SENDER};
+
+    #[test]
+    fn test_try_sender_chain_id_mismatch() {
+        let signer = Signer::new(SU256::from(5_u64));
+        for raw in [LEGACY_TX, DYNAMIC_FEE_TX] {
+            assert_eq!(
+                signer.try_sender(&decode_tx(raw)),
+                Err(SignerError::ChainIdMismatch {
+                    expect: 5_u64.into(),
+                    got: 1_u64.into(),
+                })
+            );
+        }
+    }
+
+    #[test]
+    fn test_try_sender_typed_parity() {
+        let signer = signer();
+        for raw in [ACCESS_LIST_TX, DYNAMIC_FEE_TX] {
+            let tx = decode_tx(raw);
+            let parity = tx.y_parity().unwrap();
+            assert_eq!(signer.try_sender(&tx), Ok(SENDER.into()));
+            for v in [27, 28, 2, 256] {
+                let mut tx = tx.clone();
+                match &mut tx {
+                    TransactionInner::AccessList(tx) => tx.v = (v + parity as u64).into(),
+                    TransactionInner::DynamicFee(tx) => tx.v = (v + parity as u64).into(),
+                    TransactionInner::Legacy(_) => unreachable!(),
+                }
+                assert_eq!(signer.try_sender(&tx), Err(SignerError::InvalidSignature));
+            }
+        }
+    }
+
+    #[test]
+    fn test_recover_address_wallet_v() {
+        let tx = decode_tx(DYNAMIC_FEE_TX);
+        let hash = signer().signing_hash(&tx);
+        let sig = tx.signature(1);
+        let mut sig_bytes = [0_u8; 65];
+        sig_bytes[..32].copy_from_slice(&sig.r);
+        sig_bytes[32..64].copy_from_slice(&sig.s);
+        for v in [sig.v, sig.v + 27] {
+            sig_bytes[64] = v;
+            assert_eq!(recover_address(&hash, &sig_bytes), Some(SENDER.into()));
+        }
+        sig_bytes[64] = 2;
+        assert_eq!(recover_address(&hash, &sig_bytes), None);
+    }
+
+    #[test]
+    fn test_signer_chain_id() {
+        let signer = Signer::with_chain_id(ChainId::from(5_u64));
+        assert_eq!(signer.chain_id, SU256::from(5_u64));
+        assert_eq!(signer.chain_id(), Some(ChainId::from(5_u64)));
+        assert_eq!(Signer::new(5_u64.into()).chain_id, signer.chain_id);
+
+        let signer = Signer::new(SU256::from(u64::MAX) + SU256::from(1_u64));
+        assert_eq!(signer.chain_id(), None);
+    }
+
+    #[test]
+    fn test_personal_sign_vector() {
+        // `personal_sign("Hello World")` by the key 0x0123..0123, as MetaMask
+        // and ethers' `signMessage` produce it (RFC 6979 nonce, low s)
+        let msg = b"Hello World";
+        assert_eq!(
+            hash_personal_message(msg),
+            "0xa1de988600a42c4b4ab089b619297c17d53cffae5d5120d82d8a92d0bb3b78f2".into()
+        );
+        let mut sig = [0_u8; 65];
+        sig.copy_from_slice(&hex::decode("e0ed34fbbe927a58267ce2e8067a611c69869e20e731bc99187a8bc97058664c16de07f7660f06ce0985d1d8e063726783033fda59b307897f26a21392d62b3a1c").unwrap());
+        let signer: SH160 = "0x14791697260e4c9a71f18484c9f997b308e59325".into();
+        assert_eq!(recover_personal_signature(msg, &sig), Ok(signer));
+
+        assert_ne!(
+            recover_personal_signature(b"Hello World!", &sig),
+            Ok(signer)
+        );
+        sig[64] = 29;
+        assert!(recover_personal_signature(msg, &sig).is_err());
+    }
+
+    // DYNAMIC_FEE_TX re-signed with chain id 0
+    const ZERO_CHAIN_ID_TX: &str = "0x02f86b8002847735940085174876e8008252089435353535353535353535353535353535353535350180c080a0b0e93a92b5bbd909820f906108cbea1f6e3d8e32d4a68eac41e494a42f5028d6a00af73c6b3bcc9512bfefb37fac3288462f36359fe8ec3499ed00a006733a7504";
+
+    #[test]
+    fn test_allow_zero_chain_id() {
+        let tx = decode_tx(ZERO_CHAIN_ID_TX);
+        assert_eq!(
+            signer().try_sender(&tx),
+            Err(SignerError::ChainIdMismatch {
+                expect: 1_u64.into(),
+                got: 0_u64.into(),
+            })
+        );
+
+        let wildcard = signer().with_allow_zero_chain_id(true);
+        assert_eq!(wildcard.try_sender(&tx), Ok(SENDER.into()));
+        assert_eq!(wildcard.sender(&tx), SENDER.into());
+        // other chains are still rejected
+        let tx = decode_tx(DYNAMIC_FEE_TX);
+        let other = Signer::new(SU256::from(5_u64)).with_allow_zero_chain_id(true);
+        assert!(other.try_sender(&tx).is_err());
+    }
+
+    #[test]
+    #[should_panic]
+    fn test_zero_chain_id_panics_without_flag() {
+        let tx = decode_tx(ZERO_CHAIN_ID_TX);
+        signer().sender(&tx);
+    }
+
+    #[test]
+    fn test_sender_and_hash() {
+        let signer = signer();
+        for raw in [LEGACY_TX, ACCESS_LIST_TX, DYNAMIC_FEE_TX] {
+            let tx = decode_tx(raw);
+            let (sender, hash) = signer.sender_and_hash(&tx).unwrap();
+            assert_eq!(Ok(sender), signer.try_sender(&tx));
+            assert_eq!(sender, signer.sender(&tx));
+            assert_eq!(hash, signer.signing_hash(&tx));
+        }
+        let other = Signer::new(SU256::from(5_u64));
+        assert!(other.sender_and_hash(&decode_tx(LEGACY_TX)).is_err());
+    }
 }
@@ -1,6 +1,8 @@
 use std::prelude::v1::*;
 
-use super::{TransactionAccessTuple, SH160, SH256, SU256, SU64};
+use super::{
+    trie, KeccakHasher, TransactionAccessTuple, TransactionInner, SH160, SH256, SU256, SU64,
+};
 use crypto::keccak_hash;
 use hex::HexBytes;
 use std::borrow::Cow;
@@ -40,6 +42,11 @@ impl StateAccount {
         self != &StateAccount::default()
     }
 
+    // EIP-161: no nonce, no balance and no code, whatever the storage root.
+    pub fn is_empty_eip161(&self) -> bool {
+        self.nonce == 0 && self.balance.is_zero() && self.code_hash == Self::empty_code_hash()
+    }
+
     pub fn to_bytes(&self) -> Vec<u8> {
         if self.is_exist() {
             rlp::encode(self).into()
@@ -54,6 +61,145 @@ impl StateAccount {
         }
         rlp::decode(data)
     }
+
+    pub fn verify_code(&self, code: &[u8]) -> bool {
+        if code.len() == 0 {
+            return self.code_hash == Self::empty_code_hash();
+        }
+        let hash: SH256 = keccak_hash(code).into();
+        self.code_hash == hash
+    }
+
+    pub fn next_contract_address(&self, deployer: &SH160) -> SH160 {
+        create_address(deployer, self.nonce)
+    }
+}
+
+pub fn create_address(sender: &SH160, nonce: u64) -> SH160 {
+    let mut s = rlp::RlpStream::new_list(2);
+    s.append(sender);
+    s.append(&nonce);
+    let hash = keccak_hash(&s.out());
+    SH160::from_slice(&hash[12..])
+}
+
+// Verifies that `keys`/`values` are exactly the slots of the storage trie
+// rooted at `storage_hash` between the first and the last key, as served by
+// snap sync. `keys` are the hashed trie keys (see `storage_trie_key`), not
+// the raw slots, in ascending order. An empty `proof` means the range covers
+// the whole trie, otherwise it holds the boundary proofs of both edge keys.
+pub fn verify_storage_range(
+    storage_hash: &SH256,
+    keys: &[SH256],
+    values: &[SU256],
+    proof: &[Vec<u8>],
+) -> Result<(), String> {
+    if keys.len() != values.len() {
+        return Err(format!(
+            "keys and values length mismatch: {} != {}",
+            keys.len(),
+            values.len()
+        ));
+    }
+    for i in 1..keys.len() {
+        if keys[i - 1] >= keys[i] {
+            return Err(format!("range keys are not strictly increasing at {}", i));
+        }
+    }
+    if let Some(i) = values.iter().position(|val| val.is_zero()) {
+        return Err(format!("unexpected empty value at {}", i));
+    }
+
+    let entries: Vec<_> = keys
+        .iter()
+        .zip(values)
+        .map(|(key, val)| (key.as_bytes().to_vec(), rlp::encode(val).to_vec()))
+        .collect();
+    let root: SH256 = match proof.len() {
+        0 => triehash::trie_root::<KeccakHasher, _, _, _>(entries).into(),
+        _ => {
+            let db = trie::proof_db(proof);
+            let first = match keys.first() {
+                Some(key) => trie::key_to_nibbles(key.as_bytes()),
+                None => return Err(format!("empty range proofs are not supported")),
+            };
+            let root = trie::Node::Hash(*storage_hash);
+            if let [(_, val)] = entries.as_slice() {
+                let (_, got) = trie::proof_to_path(root, &first, &db, false)?;
+                if got.as_ref() != Some(val) {
+                    return Err(format!("storage value mismatch for {:?}", keys[0]));
+                }
+                return Ok(());
+            }
+            let last = trie::key_to_nibbles(keys[keys.len() - 1].as_bytes());
+            let (root, _) = trie::proof_to_path(root, &first, &db, true)?;
+            let (mut root, _) = trie::proof_to_path(root, &last, &db, true)?;
+            if trie::unset_internal(&mut root, &first, &last, 0)? {
+                root = trie::Node::Empty;
+            }
+            for (key, val) in entries {
+                root = trie::insert(root, &trie::key_to_nibbles(&key), val)?;
+            }
+            trie::root_hash(&root)
+        }
+    };
+    if &root != storage_hash {
+        return Err(format!(
+            "storage root mismatch, want: {:?}, got: {:?}",
+            storage_hash, root
+        ));
+    }
+    Ok(())
+}
+
+pub fn create2_address(sender: &SH160, salt: &SH256, init_code_hash: &SH256) -> SH160 {
+    let mut data = Vec::with_capacity(1 + 20 + 32 + 32);
+    data.push(0xff);
+    data.extend_from_slice(sender.as_bytes());
+    data.extend_from_slice(salt.as_bytes());
+    data.extend_from_slice(init_code_hash.as_bytes());
+    let hash = keccak_hash(&data);
+    SH160::from_slice(&hash[12..])
+}
+
+// Key of a slot in the (secure) storage trie.
+pub fn storage_trie_key(slot: &SH256) -> SH256 {
+    keccak_hash(slot.as_bytes()).into()
+}
+
+// Key of an account in the (secure) state trie.
+pub fn account_trie_key(addr: &SH160) -> SH256 {
+    keccak_hash(addr.as_bytes()).into()
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum AffordError {
+    NonceMismatch { expect: u64, got: u64 },
+    InsufficientBalance { balance: SU256, cost: SU256 },
+    CostOverflow(String),
+}
+
+pub fn can_afford(
+    account: &StateAccount,
+    tx: &TransactionInner,
+    base_fee: Option<&SU256>,
+) -> Result<(), AffordError> {
+    if tx.nonce() != account.nonce {
+        return Err(AffordError::NonceMismatch {
+            expect: account.nonce,
+            got: tx.nonce(),
+        });
+    }
+    let cost = tx
+        .try_cost(base_fee.cloned())
+        .map_err(AffordError::CostOverflow)?;
+    if account.balance < cost {
+        return Err(AffordError::InsufficientBalance {
+            balance: account.balance,
+            cost,
+        });
+    }
+    Ok(())
 }
 
 pub trait StateAccountTrait:
@@ -125,7 +271,9 @@ impl StateAccountTrait for StateAccount {
     }
 }
 
-#[derive(Clone, Debug, Deserialize, Serialize, PartialEq, Eq, Default)]
+#[derive(
+    Clone, Debug, Deserialize, Serialize, PartialEq, Eq, Default, RlpEncodable, RlpDecodable,
+)]
 #[serde(rename_all = "camelCase")]
 pub struct AccountResult {
     pub address: SH160,
@@ -137,7 +285,46 @@ pub struct AccountResult {
     pub storage_proof: Vec<StorageResult>,
 }
 
-#[derive(Clone, Debug, Deserialize, Serialize, PartialEq, Eq)]
+impl AccountResult {
+    pub fn to_state_account(&self) -> StateAccount {
+        StateAccount {
+            nonce: self.nonce.as_u64(),
+            balance: self.balance,
+            root: self.storage_hash,
+            code_hash: self.code_hash,
+        }
+    }
+
+    // A compact rlp layout for local caches, proof nodes are kept as raw bytes.
+    pub fn to_compact_bytes(&self) -> Vec<u8> {
+        rlp::encode(self).to_vec()
+    }
+
+    pub fn from_compact_bytes(data: &[u8]) -> Result<Self, rlp::DecoderError> {
+        rlp::decode(data)
+    }
+
+    pub fn storage_value(&self, key: &SH256) -> Option<&SU256> {
+        self.iter_storage()
+            .find(|(slot, _)| slot == key)
+            .map(|(_, value)| value)
+    }
+
+    // Nodes may return keys without their leading zeros, so they get
+    // left-padded to 32 bytes. Keys longer than that are skipped.
+    pub fn iter_storage(&self) -> impl Iterator<Item = (SH256, &SU256)> {
+        self.storage_proof.iter().filter_map(|item| {
+            if item.key.len() > 32 {
+                return None;
+            }
+            let mut key = SH256::default();
+            key.as_bytes_mut()[32 - item.key.len()..].copy_from_slice(&item.key);
+            Some((key, &item.value))
+        })
+    }
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize, PartialEq, Eq, RlpEncodable, RlpDecodable)]
 #[serde(rename_all = "camelCase")]
 pub struct StorageResult {
     pub key: HexBytes,
@@ -151,6 +338,31 @@ pub struct FetchStateResult {
     pub code: Option<HexBytes>,
 }
 
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum StateError {
+    CodeHashMismatch { want: SH256, got: SH256 },
+    CodeWithoutAccount,
+}
+
+impl FetchStateResult {
+    pub fn verify_code(&self) -> Result<(), StateError> {
+        match (&self.acc, &self.code) {
+            (Some(acc), Some(code)) => {
+                let hash: SH256 = keccak_hash(code).into();
+                if hash != acc.code_hash {
+                    return Err(StateError::CodeHashMismatch {
+                        want: acc.code_hash,
+                        got: hash,
+                    });
+                }
+                Ok(())
+            }
+            (None, Some(_)) => Err(StateError::CodeWithoutAccount),
+            _ => Ok(()),
+        }
+    }
+}
+
 #[derive(Debug, Clone)]
 pub struct FetchState<'a> {
     pub access_list: Option<Cow<'a, TransactionAccessTuple>>,
@@ -188,7 +400,7 @@ impl<'a> FetchState<'a> {
                     return;
                 }
                 for key in &b.storage_keys {
-                    if a.storage_keys.contains(&key) {
+                    if !a.storage_keys.contains(&key) {
                         a.to_mut().storage_keys.push(key.clone());
                     }
                 }
@@ -197,6 +409,37 @@ impl<'a> FetchState<'a> {
     }
 }
 
+// Accumulates `FetchState` requests, merging the ones for the same address.
+#[derive(Debug, Clone, Default)]
+pub struct FetchStateList<'a> {
+    items: Vec<FetchState<'a>>,
+}
+
+impl<'a> FetchStateList<'a> {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn push(&mut self, item: FetchState<'a>) {
+        match self.items.iter_mut().find(|n| n.is_match(&item)) {
+            Some(n) => n.merge(item),
+            None => self.items.push(item),
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.items.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.items.is_empty()
+    }
+
+    pub fn into_requests(self) -> Vec<FetchState<'a>> {
+        self.items
+    }
+}
+
 #[derive(Clone, Debug, Deserialize, Serialize, PartialEq, Eq)]
 #[serde(rename_all = "camelCase")]
 pub struct AccessListResult {
@@ -229,3 +472,570 @@ impl AccessListResult {
         }
     }
 }
+
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct DirtyAccount {
+    pub account: StateAccount,
+    pub dirty: bool,
+}
+
+impl DirtyAccount {
+    pub fn new(account: StateAccount) -> Self {
+        Self {
+            account,
+            dirty: false,
+        }
+    }
+
+    pub fn is_dirty(&self) -> bool {
+        self.dirty
+    }
+
+    pub fn clear_dirty(&mut self) {
+        self.dirty = false;
+    }
+
+    pub fn set_balance(&mut self, val: SU256) -> bool {
+        let changed = self.account.set_balance(val);
+        self.dirty |= changed;
+        changed
+    }
+
+    pub fn set_nonce(&mut self, val: u64) -> bool {
+        let changed = self.account.set_nonce(val);
+        self.dirty |= changed;
+        changed
+    }
+
+    pub fn set_code(&mut self, code: &[u8]) -> bool {
+        let changed = self.account.set_code(code);
+        self.dirty |= changed;
+        changed
+    }
+
+    pub fn update_root(&mut self, root: SH256) -> bool {
+        let changed = self.account.update_root(root);
+        self.dirty |= changed;
+        changed
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tx::tests::{decode_tx, CREATE_TX, LEGACY_TX, SENDER};
+
+    fn slot(i: u64) -> SH256 {
+        let mut slot = [0_u8; 32];
+        slot[24..].copy_from_slice(&i.to_be_bytes());
+        slot.into()
+    }
+
+    struct Storage {
+        root: trie::Node,
+        keys: Vec<SH256>,
+        values: Vec<SU256>,
+    }
+
+    impl Storage {
+        fn new(n: u64) -> Self {
+            let mut slots: Vec<_> = (0..n)
+                .map(|i| (storage_trie_key(&slot(i)), i + 1))
+                .collect();
+            slots.sort();
+            let mut root = trie::Node::Empty;
+            for (key, val) in &slots {
+                let val = rlp::encode(&SU256::from(*val)).to_vec();
+                root = trie::insert(root, &trie::key_to_nibbles(key.as_bytes()), val).unwrap();
+            }
+            Self {
+                root,
+                keys: slots.iter().map(|(key, _)| *key).collect(),
+                values: slots.iter().map(|(_, val)| SU256::from(*val)).collect(),
+            }
+        }
+
+        fn hash(&self) -> SH256 {
+            trie::root_hash(&self.root)
+        }
+
+        fn verify(&self, start: usize, end: usize) -> Result<(), String> {
+            let (keys, values) = (&self.keys[start..end], &self.values[start..end]);
+            let mut proof = trie::prove(&self.root, &trie::key_to_nibbles(keys[0].as_bytes()));
+            let last = keys[keys.len() - 1];
+            proof.extend(trie::prove(
+                &self.root,
+                &trie::key_to_nibbles(last.as_bytes()),
+            ));
+            verify_storage_range(&self.hash(), keys, values, &proof)
+        }
+    }
+
+    #[test]
+    fn test_verify_storage_range_whole_trie() {
+        let storage = Storage::new(20);
+        verify_storage_range(&storage.hash(), &storage.keys, &storage.values, &[]).unwrap();
+
+        let mut values = storage.values.clone();
+        values[3] = 100.into();
+        assert!(verify_storage_range(&storage.hash(), &storage.keys, &values, &[]).is_err());
+    }
+
+    #[test]
+    fn test_verify_storage_range_with_proof() {
+        let storage = Storage::new(100);
+        for (start, end) in [(0, 100), (0, 10), (10, 20), (90, 100), (45, 47), (0, 2)] {
+            storage.verify(start, end).unwrap();
+        }
+        // single slot
+        storage.verify(42, 43).unwrap();
+    }
+
+    #[test]
+    fn test_verify_storage_range_gap() {
+        let storage = Storage::new(100);
+        let mut keys = storage.keys[10..20].to_vec();
+        let mut values = storage.values[10..20].to_vec();
+        keys.remove(5);
+        values.remove(5);
+        let mut proof = trie::prove(&storage.root, &trie::key_to_nibbles(keys[0].as_bytes()));
+        let last = trie::key_to_nibbles(keys[keys.len() - 1].as_bytes());
+        proof.extend(trie::prove(&storage.root, &last));
+        let err = verify_storage_range(&storage.hash(), &keys, &values, &proof).unwrap_err();
+        assert!(err.starts_with("storage root mismatch"), "{}", err);
+    }
+
+    #[test]
+    fn test_verify_storage_range_bad_input() {
+        let storage = Storage::new(10);
+        let hash = storage.hash();
+        let (keys, values) = (&storage.keys, &storage.values);
+
+        let mut bad = values.clone();
+        bad[4] = 1000.into();
+        let proof = trie::prove(&storage.root, &trie::key_to_nibbles(keys[0].as_bytes()));
+        assert!(verify_storage_range(&hash, &keys[..6], &bad[..6], &proof).is_err());
+
+        let reversed: Vec<_> = keys.iter().rev().cloned().collect();
+        assert!(verify_storage_range(&hash, &reversed, values, &[]).is_err());
+        assert!(verify_storage_range(&hash, &keys[..2], &values[..1], &[]).is_err());
+
+        let mut zero = values.clone();
+        zero[0] = SU256::zero();
+        assert!(verify_storage_range(&hash, keys, &zero, &[]).is_err());
+
+        // a proof that does not lead to the root
+        let other = Storage::new(11);
+        assert!(other.verify(0, 5).is_ok());
+        assert!(verify_storage_range(&hash, &other.keys[..5], &other.values[..5], &proof).is_err());
+    }
+
+    #[test]
+    fn test_trie_keys() {
+        assert_eq!(
+            storage_trie_key(&slot(0)),
+            "0x290decd9548b62a8d60345a988386fc84ba6bc95484008f6362f93160ef3e563".into()
+        );
+        assert_eq!(
+            storage_trie_key(&slot(1)),
+            "0xb10e2d527612073b26eecdfd717e6a320cf44b4afac2b0732d9fcbe2b7fa0cf6".into()
+        );
+        assert_eq!(
+            account_trie_key(&SH160::default()),
+            "0x5380c7b7ae81a58eb98d9c78de4a1fd7fd9535fc953ed2be602daaa41767312a".into()
+        );
+    }
+
+    #[test]
+    fn test_create_address() {
+        let sender: SH160 = "0x6ac7ea33f8831ea9dcc53393aaa88b25a785dbf0".into();
+        for (nonce, addr) in [
+            (0, "0xcd234a471b72ba2f1ccf0a70fcaba648a5eecd8d"),
+            (1, "0x343c43a37d37dff08ae8c4a11544c718abb4fcf8"),
+            (2, "0xf778b86fa74e846c4f0a1fbd1335fe81c00a0c91"),
+            (3, "0xfffd933a0bc612844eaf0c6fe3e5b8e9b6c1d19c"),
+        ] {
+            assert_eq!(create_address(&sender, nonce), addr.into());
+        }
+
+        let tx = decode_tx(CREATE_TX);
+        let sender: SH160 = SENDER.into();
+        assert_eq!(
+            tx.created_contract(&sender),
+            Some("0x34b544d335dc19a7cbef592c72e74c9745e603bd".into())
+        );
+        let tx = decode_tx(LEGACY_TX);
+        assert_eq!(tx.created_contract(&sender), None);
+    }
+
+    #[test]
+    fn test_create2_address() {
+        // the examples from EIP-1014
+        let zero = "0x0000000000000000000000000000000000000000";
+        let deadbeef = "0xdeadbeef00000000000000000000000000000000";
+        let cafebabe = "0x00000000000000000000000000000000000000000000000000000000cafebabe";
+        for (sender, salt, init_code, addr) in [
+            (zero, "0x00", "00", "0x4d1a2e2bb4f88f0250f26ffff098b0b30b26bf38"),
+            (deadbeef, "0x00", "00", "0xb928f69bb1d91cd65274e3c79d8986362984fda3"),
+            (
+                deadbeef,
+                "0x000000000000000000000000feed000000000000000000000000000000000000",
+                "00",
+                "0xd04116cdd17bebe565eb2422f2497e06cc1c9833",
+            ),
+            (zero, "0x00", "deadbeef", "0x70f2b2914a2a4b783faefb75f459a580616fcb5e"),
+            (
+                "0x00000000000000000000000000000000deadbeef",
+                cafebabe,
+                "deadbeef",
+                "0x60f3f640a8508fc6a86d45df051962668e1e8ac7",
+            ),
+            (
+                "0x00000000000000000000000000000000deadbeef",
+                cafebabe,
+                "deadbeefdeadbeefdeadbeefdeadbeefdeadbeefdeadbeefdeadbeefdeadbeefdeadbeefdeadbeefdeadbeef",
+                "0x1d8bfdc5d46dc4f61d6b6115972536ebe6a8854c",
+            ),
+            (zero, "0x00", "", "0xe33c0c7f7df4809055c3eba6c09cfe4baf1bd9e0"),
+        ] {
+            let init_code_hash: SH256 = keccak_hash(&hex::decode(init_code).unwrap()).into();
+            assert_eq!(
+                create2_address(&sender.into(), &salt.into(), &init_code_hash),
+                addr.into()
+            );
+        }
+    }
+
+    #[test]
+    fn test_dirty_account() {
+        let mut acc = DirtyAccount::new(StateAccount::default());
+        assert!(!acc.is_dirty());
+        assert!(!acc.set_balance(SU256::zero()));
+        assert!(!acc.set_nonce(0));
+        assert!(!acc.set_code(&[]));
+        assert!(!acc.update_root(StateAccount::empty_root()));
+        assert!(!acc.is_dirty());
+
+        assert!(acc.set_balance(1_u64.into()));
+        assert!(acc.is_dirty());
+        assert_eq!(acc.account.balance, SU256::from(1_u64));
+        acc.clear_dirty();
+        assert!(!acc.set_balance(1_u64.into()));
+        assert!(!acc.is_dirty());
+
+        let mut nonce = acc.clone();
+        assert!(nonce.set_nonce(1) && nonce.is_dirty());
+        let mut code = acc.clone();
+        assert!(code.set_code(&[0x60]) && code.is_dirty());
+        assert!(acc.update_root(SH256::default()) && acc.is_dirty());
+    }
+
+    #[test]
+    fn test_next_contract_address() {
+        let deployer: SH160 = "0x6ac7ea33f8831ea9dcc53393aaa88b25a785dbf0".into();
+        let mut acc = StateAccount::default();
+        assert_eq!(
+            acc.next_contract_address(&deployer),
+            "0xcd234a471b72ba2f1ccf0a70fcaba648a5eecd8d".into()
+        );
+        acc.set_nonce(1);
+        assert_eq!(
+            acc.next_contract_address(&deployer),
+            "0x343c43a37d37dff08ae8c4a11544c718abb4fcf8".into()
+        );
+        assert_eq!(
+            acc.next_contract_address(&deployer),
+            create_address(&deployer, 1)
+        );
+    }
+
+    #[test]
+    fn test_state_account_verify_code() {
+        let code = hex::decode("60006000f3").unwrap();
+        let contract = StateAccount {
+            nonce: 1,
+            code_hash: keccak_hash(&code).into(),
+            ..Default::default()
+        };
+        assert!(contract.verify_code(&code));
+        assert!(!contract.verify_code(&code[..4]));
+        assert!(!contract.verify_code(&[]));
+
+        let eoa = StateAccount::default();
+        assert!(eoa.verify_code(&[]));
+        assert!(!eoa.verify_code(&code));
+    }
+
+    #[test]
+    fn test_account_result_to_state_account() {
+        // shape of an eth_getProof response, proof nodes trimmed
+        let result: AccountResult = serde_json::from_value(serde_json::json!({
+            "address": "0x7f0d15c7faae65896648c8273b6d7e43f58fa842",
+            "accountProof": ["0xf90211a0"],
+            "balance": "0xde0b6b3a7640000",
+            "codeHash": "0xc5d2460186f7233c927e7db2dcc703c0e500b653ca82273b7bfad8045d85a470",
+            "nonce": "0x2a",
+            "storageHash": "0x1111111111111111111111111111111111111111111111111111111111111111",
+            "storageProof": [{
+                "key": "0x0000000000000000000000000000000000000000000000000000000000000000",
+                "value": "0x1",
+                "proof": ["0xe2a0"],
+            }],
+        }))
+        .unwrap();
+        let account = result.to_state_account();
+        assert_eq!(
+            account,
+            StateAccount {
+                nonce: 42,
+                balance: SU256::from(1_000_000_000_000_000_000_u64),
+                root: "0x1111111111111111111111111111111111111111111111111111111111111111".into(),
+                code_hash: StateAccount::empty_code_hash(),
+            }
+        );
+        assert!(account.is_exist());
+        assert_eq!(
+            AccountResult::default().to_state_account().code_hash,
+            SH256::default()
+        );
+    }
+
+    #[test]
+    fn test_can_afford() {
+        // nonce 9, 21000 gas at 20 gwei, sends 1 ether
+        let tx = decode_tx(LEGACY_TX);
+        let cost = SU256::from(1_000_420_000_000_000_000_u64);
+        let account = StateAccount {
+            nonce: 9,
+            balance: cost,
+            ..Default::default()
+        };
+        assert_eq!(can_afford(&account, &tx, None), Ok(()));
+
+        let poor = StateAccount {
+            balance: cost - SU256::from(1_u64),
+            ..account.clone()
+        };
+        assert_eq!(
+            can_afford(&poor, &tx, None),
+            Err(AffordError::InsufficientBalance {
+                balance: poor.balance,
+                cost,
+            })
+        );
+
+        let stale = StateAccount {
+            nonce: 8,
+            ..account.clone()
+        };
+        assert_eq!(
+            can_afford(&stale, &tx, None),
+            Err(AffordError::NonceMismatch { expect: 8, got: 9 })
+        );
+
+        let mut expensive = tx;
+        if let TransactionInner::Legacy(tx) = &mut expensive {
+            tx.gas_price = crate::U256::max_value().into();
+        }
+        assert!(matches!(
+            can_afford(&account, &expensive, None),
+            Err(AffordError::CostOverflow(_))
+        ));
+    }
+
+    #[test]
+    fn test_account_result_compact_bytes() {
+        // a typical eth_getProof result: a handful of full branch nodes per proof
+        let node = |i: u8| HexBytes::from(vec![i; 532]);
+        let result = AccountResult {
+            address: "0x7f0d15c7faae65896648c8273b6d7e43f58fa842".into(),
+            account_proof: (0..8).map(node).collect(),
+            balance: SU256::from(1_000_000_000_000_000_000_u64),
+            code_hash: "0x1111111111111111111111111111111111111111111111111111111111111111".into(),
+            nonce: 42_u64.into(),
+            storage_hash: "0x2222222222222222222222222222222222222222222222222222222222222222"
+                .into(),
+            storage_proof: vec![
+                StorageResult {
+                    key: slot(0).as_bytes().to_vec().into(),
+                    value: 1_u64.into(),
+                    proof: (8..14).map(node).collect(),
+                },
+                StorageResult {
+                    key: vec![0x01].into(),
+                    value: SU256::zero(),
+                    proof: vec![],
+                },
+            ],
+        };
+        let data = result.to_compact_bytes();
+        assert_eq!(AccountResult::from_compact_bytes(&data).unwrap(), result);
+        let json = serde_json::to_vec(&result).unwrap();
+        assert!(
+            data.len() * 2 < json.len(),
+            "{} vs {}",
+            data.len(),
+            json.len()
+        );
+
+        let empty = AccountResult::default();
+        assert_eq!(
+            AccountResult::from_compact_bytes(&empty.to_compact_bytes()).unwrap(),
+            empty
+        );
+        assert!(AccountResult::from_compact_bytes(&data[..data.len() - 1]).is_err());
+    }
+
+    #[test]
+    fn test_storage_value() {
+        let item = |key: &str, value: u64| StorageResult {
+            key: hex::decode(key).unwrap().into(),
+            value: value.into(),
+            proof: vec![],
+        };
+        let result = AccountResult {
+            storage_proof: vec![
+                item(
+                    "0000000000000000000000000000000000000000000000000000000000000002",
+                    7,
+                ),
+                // leading zeros stripped by the node
+                item("05", 9),
+                // longer than a slot
+                item(&"ff".repeat(33), 11),
+            ],
+            ..Default::default()
+        };
+        assert_eq!(result.storage_value(&slot(2)), Some(&SU256::from(7_u64)));
+        assert_eq!(result.storage_value(&slot(5)), Some(&SU256::from(9_u64)));
+        assert_eq!(result.storage_value(&slot(3)), None);
+
+        let slots: Vec<_> = result
+            .iter_storage()
+            .map(|(key, val)| (key, *val))
+            .collect();
+        assert_eq!(
+            slots,
+            vec![(slot(2), 7_u64.into()), (slot(5), 9_u64.into())]
+        );
+    }
+
+    #[test]
+    fn test_fetch_state_result_verify_code() {
+        let code = hex::decode("60006000f3").unwrap();
+        let code_hash: SH256 = keccak_hash(&code).into();
+        let acc = AccountResult {
+            code_hash,
+            ..Default::default()
+        };
+        let result = FetchStateResult {
+            acc: Some(acc.clone()),
+            code: Some(code.clone().into()),
+        };
+        assert_eq!(result.verify_code(), Ok(()));
+
+        let other = vec![0x60, 0x01];
+        let result = FetchStateResult {
+            acc: Some(acc.clone()),
+            code: Some(other.clone().into()),
+        };
+        assert_eq!(
+            result.verify_code(),
+            Err(StateError::CodeHashMismatch {
+                want: code_hash,
+                got: keccak_hash(&other).into(),
+            })
+        );
+
+        let result = FetchStateResult {
+            acc: None,
+            code: Some(code.into()),
+        };
+        assert_eq!(result.verify_code(), Err(StateError::CodeWithoutAccount));
+
+        // nothing to compare
+        let result = FetchStateResult {
+            acc: Some(acc),
+            code: None,
+        };
+        assert_eq!(result.verify_code(), Ok(()));
+        assert_eq!(FetchStateResult::default().verify_code(), Ok(()));
+    }
+
+    #[test]
+    fn test_fetch_state_list() {
+        let a: SH160 = "0x00000000000000000000000000000000000000aa".into();
+        let b: SH160 = "0x00000000000000000000000000000000000000bb".into();
+        let tuple = |address: SH160, keys: &[u64]| TransactionAccessTuple {
+            address,
+            storage_keys: keys.iter().map(|i| slot(*i)).collect(),
+        };
+        let borrowed = tuple(a, &[1, 2]);
+
+        let mut list = FetchStateList::new();
+        assert!(list.is_empty());
+        list.push(FetchState {
+            access_list: Some(Cow::Borrowed(&borrowed)),
+            code: None,
+        });
+        list.push(FetchState {
+            access_list: None,
+            code: Some(b),
+        });
+        list.push(FetchState {
+            access_list: Some(Cow::Owned(tuple(a, &[2, 3]))),
+            code: Some(a),
+        });
+        list.push(FetchState {
+            access_list: Some(Cow::Owned(tuple(b, &[4]))),
+            code: None,
+        });
+        list.push(FetchState {
+            access_list: None,
+            code: Some(a),
+        });
+        assert_eq!(list.len(), 2);
+
+        let requests = list.into_requests();
+        assert_eq!(requests[0].get_addr(), Some(&a));
+        assert_eq!(requests[0].code, Some(a));
+        assert_eq!(
+            requests[0].access_list.as_deref(),
+            Some(&tuple(a, &[1, 2, 3]))
+        );
+        assert_eq!(requests[1].get_addr(), Some(&b));
+        assert_eq!(requests[1].code, Some(b));
+        assert_eq!(requests[1].access_list.as_deref(), Some(&tuple(b, &[4])));
+        // the borrowed tuple was copied, not modified
+        assert_eq!(borrowed, tuple(a, &[1, 2]));
+    }
+
+    #[test]
+    fn test_is_empty_eip161() {
+        assert!(StateAccount::default().is_empty_eip161());
+
+        // only storage left behind
+        let storage_only = StateAccount {
+            root: "0x1111111111111111111111111111111111111111111111111111111111111111".into(),
+            ..Default::default()
+        };
+        assert!(storage_only.is_empty_eip161());
+        assert!(storage_only.is_exist());
+
+        let funded = StateAccount {
+            balance: 1_u64.into(),
+            ..Default::default()
+        };
+        assert!(!funded.is_empty_eip161());
+        let used = StateAccount {
+            nonce: 1,
+            ..Default::default()
+        };
+        assert!(!used.is_empty_eip161());
+        let contract = StateAccount {
+            code_hash: keccak_hash(&[0x60]).into(),
+            ..Default::default()
+        };
+        assert!(!contract.is_empty_eip161());
+    }
+}
@@ -0,0 +1,184 @@
+use std::prelude::v1::*;
+
+use core::fmt::Debug;
+
+/// Asserts that `value` survives an RLP encode/decode cycle unchanged and
+/// that re-encoding the decoded value yields the same bytes.
+pub fn prop_assert_rlp_roundtrip<T>(value: &T)
+where
+    T: rlp::Encodable + rlp::Decodable + PartialEq + Debug,
+{
+    let encoded = rlp::encode(value);
+    let decoded: T = match rlp::decode(&encoded) {
+        Ok(decoded) => decoded,
+        Err(err) => panic!(
+            "rlp decode failed: {:?}, value: {:?}, encoded: {}",
+            err,
+            value,
+            hex::encode(&encoded)
+        ),
+    };
+    assert_eq!(&decoded, value, "rlp roundtrip mismatch");
+    assert_eq!(
+        rlp::encode(&decoded).to_vec(),
+        encoded.to_vec(),
+        "rlp re-encoding mismatch"
+    );
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        AccessListTx, DynamicFeeTx, HexBytes, LegacyTx, Nilable, StateAccount,
+        TransactionAccessTuple, Withdrawal, SH160, SH256, SU256, SU64, U256,
+    };
+
+    const CASES: usize = 100;
+
+    // splitmix64, so every run sees the same cases for a given seed
+    struct Rng(u64);
+
+    impl Rng {
+        fn u64(&mut self) -> u64 {
+            self.0 = self.0.wrapping_add(0x9e3779b97f4a7c15);
+            let mut z = self.0;
+            z = (z ^ (z >> 30)).wrapping_mul(0xbf58476d1ce4e5b9);
+            z = (z ^ (z >> 27)).wrapping_mul(0x94d049bb133111eb);
+            z ^ (z >> 31)
+        }
+
+        fn below(&mut self, n: u64) -> usize {
+            (self.u64() % n) as usize
+        }
+
+        fn bytes(&mut self, len: usize) -> Vec<u8> {
+            (0..len).map(|_| self.u64() as u8).collect()
+        }
+
+        // a random byte length, so small and zero values show up too
+        fn su64(&mut self) -> SU64 {
+            (self.u64() >> (self.below(8) * 8)).into()
+        }
+
+        fn su256(&mut self) -> SU256 {
+            let len = self.below(33);
+            U256::from_big_endian(&self.bytes(len)).into()
+        }
+
+        fn sh160(&mut self) -> SH160 {
+            SH160::from_slice(&self.bytes(20))
+        }
+
+        fn sh256(&mut self) -> SH256 {
+            SH256::from_slice(&self.bytes(32))
+        }
+
+        fn to(&mut self) -> Nilable<SH160> {
+            match self.below(4) {
+                0 => None.into(),
+                _ => Some(self.sh160()).into(),
+            }
+        }
+
+        fn data(&mut self) -> HexBytes {
+            // cross the 55 byte short/long string boundary
+            let len = self.below(80);
+            self.bytes(len).into()
+        }
+
+        fn access_list(&mut self) -> Vec<TransactionAccessTuple> {
+            (0..self.below(4))
+                .map(|_| TransactionAccessTuple {
+                    address: self.sh160(),
+                    storage_keys: (0..self.below(4)).map(|_| self.sh256()).collect(),
+                })
+                .collect()
+        }
+    }
+
+    #[test]
+    fn test_prop_legacy_tx() {
+        let mut rng = Rng(0x1e9ac7);
+        for _ in 0..CASES {
+            prop_assert_rlp_roundtrip(&LegacyTx {
+                nonce: rng.su64(),
+                gas_price: rng.su256(),
+                gas: rng.su64(),
+                to: rng.to(),
+                value: rng.su256(),
+                data: rng.data(),
+                v: rng.su256(),
+                r: rng.su256(),
+                s: rng.su256(),
+            });
+        }
+    }
+
+    #[test]
+    fn test_prop_access_list_tx() {
+        let mut rng = Rng(0x2930);
+        for _ in 0..CASES {
+            prop_assert_rlp_roundtrip(&AccessListTx {
+                chain_id: rng.su256(),
+                nonce: rng.su64(),
+                gas_price: rng.su256(),
+                gas: rng.su64(),
+                to: rng.to(),
+                value: rng.su256(),
+                data: rng.data(),
+                access_list: rng.access_list(),
+                v: SU256::from(rng.below(2) as u64),
+                r: rng.su256(),
+                s: rng.su256(),
+            });
+        }
+    }
+
+    #[test]
+    fn test_prop_dynamic_fee_tx() {
+        let mut rng = Rng(0x1559);
+        for _ in 0..CASES {
+            prop_assert_rlp_roundtrip(&DynamicFeeTx {
+                chain_id: rng.su256(),
+                nonce: rng.su64(),
+                max_priority_fee_per_gas: rng.su256(),
+                max_fee_per_gas: rng.su256(),
+                gas: rng.su64(),
+                to: rng.to(),
+                value: rng.su256(),
+                data: rng.data(),
+                access_list: rng.access_list(),
+                v: SU256::from(rng.below(2) as u64),
+                r: rng.su256(),
+                s: rng.su256(),
+            });
+        }
+    }
+
+    #[test]
+    fn test_prop_withdrawal() {
+        let mut rng = Rng(0x4895);
+        for _ in 0..CASES {
+            prop_assert_rlp_roundtrip(&Withdrawal {
+                index: rng.su64(),
+                validator_index: rng.su64(),
+                address: rng.sh160(),
+                amount: rng.su64(),
+            });
+        }
+    }
+
+    #[test]
+    fn test_prop_state_account() {
+        let mut rng = Rng(0x57a7e);
+        for _ in 0..CASES {
+            prop_assert_rlp_roundtrip(&StateAccount {
+                nonce: rng.su64().as_u64(),
+                balance: rng.su256(),
+                root: rng.sh256(),
+                code_hash: rng.sh256(),
+            });
+        }
+    }
+}
@@ -3,3 +3,9 @@ use crate::SH256;
 pub trait Hasher {
     fn hash(&self) -> SH256;
 }
+
+// Resolves 4-byte method ids to human-readable signatures, backed by
+// whatever database the caller has at hand.
+pub trait MethodRegistry {
+    fn name(&self, id: &[u8; 4]) -> Option<&str>;
+}
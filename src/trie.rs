@@ -0,0 +1,470 @@
+use std::prelude::v1::*;
+
+use crate::SH256;
+use crypto::keccak_hash;
+use std::collections::BTreeMap;
+
+// A minimal in-memory Merkle Patricia trie, just enough to stitch boundary
+// proofs together and recompute a root (see geth's trie/proof.go).
+//
+// Keys are nibble paths; a leaf path carries the terminator nibble 16.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub(crate) enum Node {
+    Empty,
+    Short(Vec<u8>, Box<Node>),
+    // 16 children plus the value slot
+    Full(Vec<Node>),
+    Hash(SH256),
+    Value(Vec<u8>),
+}
+
+const TERMINATOR: u8 = 16;
+
+pub(crate) fn key_to_nibbles(key: &[u8]) -> Vec<u8> {
+    let mut out = Vec::with_capacity(key.len() * 2 + 1);
+    for b in key {
+        out.push(b >> 4);
+        out.push(b & 0x0f);
+    }
+    out.push(TERMINATOR);
+    out
+}
+
+fn compact_encode(nibbles: &[u8]) -> Vec<u8> {
+    let (nibbles, flag) = match nibbles.last() {
+        Some(&TERMINATOR) => (&nibbles[..nibbles.len() - 1], 2),
+        _ => (nibbles, 0),
+    };
+    let mut out = Vec::with_capacity(nibbles.len() / 2 + 1);
+    let rest = if nibbles.len() % 2 == 1 {
+        out.push(((flag + 1) << 4) | nibbles[0]);
+        &nibbles[1..]
+    } else {
+        out.push(flag << 4);
+        nibbles
+    };
+    for pair in rest.chunks(2) {
+        out.push((pair[0] << 4) | pair[1]);
+    }
+    out
+}
+
+fn compact_decode(data: &[u8]) -> Result<(Vec<u8>, bool), String> {
+    if data.is_empty() {
+        return Err("empty compact key".into());
+    }
+    let flag = data[0] >> 4;
+    if flag > 3 {
+        return Err(format!("invalid compact key flag: {}", flag));
+    }
+    let leaf = flag & 2 != 0;
+    let mut out = Vec::with_capacity(data.len() * 2);
+    if flag & 1 != 0 {
+        out.push(data[0] & 0x0f);
+    }
+    for b in &data[1..] {
+        out.push(b >> 4);
+        out.push(b & 0x0f);
+    }
+    if leaf {
+        out.push(TERMINATOR);
+    }
+    Ok((out, leaf))
+}
+
+fn rlp_err(err: rlp::DecoderError) -> String {
+    format!("{:?}", err)
+}
+
+fn decode_ref(rlp: &rlp::Rlp) -> Result<Node, String> {
+    if rlp.is_list() {
+        // nodes shorter than 32 bytes are embedded in their parent
+        return decode_node(rlp.as_raw());
+    }
+    let data = rlp.data().map_err(rlp_err)?;
+    match data.len() {
+        0 => Ok(Node::Empty),
+        32 => Ok(Node::Hash(SH256::from_slice(data))),
+        n => Err(format!("invalid node reference length: {}", n)),
+    }
+}
+
+pub(crate) fn decode_node(buf: &[u8]) -> Result<Node, String> {
+    let rlp = rlp::Rlp::new(buf);
+    let count = rlp.item_count().map_err(rlp_err)?;
+    let item = |i: usize| rlp.at(i).map_err(rlp_err);
+    match count {
+        2 => {
+            let key = item(0)?.data().map_err(rlp_err)?.to_vec();
+            let (key, leaf) = compact_decode(&key)?;
+            let val = if leaf {
+                let val = item(1)?.data().map_err(rlp_err)?;
+                Node::Value(val.to_vec())
+            } else {
+                decode_ref(&item(1)?)?
+            };
+            Ok(Node::Short(key, Box::new(val)))
+        }
+        17 => {
+            let mut children = Vec::with_capacity(17);
+            for i in 0..16 {
+                children.push(decode_ref(&item(i)?)?);
+            }
+            let val = item(16)?.data().map_err(rlp_err)?;
+            children.push(match val.len() {
+                0 => Node::Empty,
+                _ => Node::Value(val.to_vec()),
+            });
+            Ok(Node::Full(children))
+        }
+        n => Err(format!("invalid number of list elements: {}", n)),
+    }
+}
+
+fn append_ref(s: &mut rlp::RlpStream, node: &Node) {
+    match node {
+        Node::Empty => {
+            s.append_empty_data();
+        }
+        Node::Hash(hash) => {
+            s.append(&hash.as_bytes());
+        }
+        Node::Value(val) => {
+            s.append(val);
+        }
+        node => {
+            let enc = encode_node(node);
+            if enc.len() < 32 {
+                s.append_raw(&enc, 1);
+            } else {
+                s.append(&&keccak_hash(&enc)[..]);
+            }
+        }
+    }
+}
+
+pub(crate) fn encode_node(node: &Node) -> Vec<u8> {
+    let mut s = rlp::RlpStream::new();
+    match node {
+        Node::Short(key, val) => {
+            s.begin_list(2);
+            s.append(&compact_encode(key));
+            append_ref(&mut s, val);
+        }
+        Node::Full(children) => {
+            s.begin_list(17);
+            for child in children {
+                append_ref(&mut s, child);
+            }
+        }
+        Node::Empty => {
+            s.append_empty_data();
+        }
+        Node::Hash(hash) => {
+            s.append(&hash.as_bytes());
+        }
+        Node::Value(val) => {
+            s.append(val);
+        }
+    }
+    s.out().to_vec()
+}
+
+pub(crate) fn root_hash(node: &Node) -> SH256 {
+    match node {
+        Node::Hash(hash) => *hash,
+        node => keccak_hash(&encode_node(node)).into(),
+    }
+}
+
+pub(crate) fn insert(node: Node, key: &[u8], value: Vec<u8>) -> Result<Node, String> {
+    match node {
+        Node::Empty => Ok(Node::Short(key.to_vec(), Box::new(Node::Value(value)))),
+        Node::Value(_) if key.is_empty() || key == [TERMINATOR] => Ok(Node::Value(value)),
+        Node::Short(path, val) => {
+            let matched = path.iter().zip(key).take_while(|(a, b)| a == b).count();
+            if matched == path.len() {
+                let val = insert(*val, &key[matched..], value)?;
+                return Ok(Node::Short(path, Box::new(val)));
+            }
+            let mut children = vec![Node::Empty; 17];
+            children[path[matched] as usize] = match path.len() - matched - 1 {
+                0 => *val,
+                _ => Node::Short(path[matched + 1..].to_vec(), val),
+            };
+            children[key[matched] as usize] = insert(Node::Empty, &key[matched + 1..], value)?;
+            let branch = Node::Full(children);
+            match matched {
+                0 => Ok(branch),
+                _ => Ok(Node::Short(path[..matched].to_vec(), Box::new(branch))),
+            }
+        }
+        Node::Full(mut children) => {
+            let idx = key[0] as usize;
+            if idx == TERMINATOR as usize {
+                children[idx] = Node::Value(value);
+            } else {
+                let child = std::mem::replace(&mut children[idx], Node::Empty);
+                children[idx] = insert(child, &key[1..], value)?;
+            }
+            Ok(Node::Full(children))
+        }
+        Node::Hash(hash) => Err(format!("missing trie node {:?}", hash)),
+        Node::Value(_) => Err("key is longer than the trie path".into()),
+    }
+}
+
+pub(crate) type ProofDb = BTreeMap<SH256, Vec<u8>>;
+
+pub(crate) fn proof_db(proof: &[Vec<u8>]) -> ProofDb {
+    proof
+        .iter()
+        .map(|node| (keccak_hash(node).into(), node.clone()))
+        .collect()
+}
+
+fn resolve(hash: &SH256, db: &ProofDb) -> Result<Node, String> {
+    match db.get(hash) {
+        Some(buf) => decode_node(buf),
+        None => Err(format!("proof node (hash {:?}) missing", hash)),
+    }
+}
+
+// Resolves the nodes along `key` from the proof, returning the (partially
+// resolved) trie and the value stored at `key` if the proof contains it.
+pub(crate) fn proof_to_path(
+    node: Node,
+    key: &[u8],
+    db: &ProofDb,
+    allow_non_existent: bool,
+) -> Result<(Node, Option<Vec<u8>>), String> {
+    let not_found = |node: Node| match allow_non_existent {
+        true => Ok((node, None)),
+        false => Err("the node is not contained in trie".into()),
+    };
+    match node {
+        Node::Hash(hash) => proof_to_path(resolve(&hash, db)?, key, db, allow_non_existent),
+        Node::Empty => not_found(Node::Empty),
+        Node::Value(val) => Ok((Node::Value(val.clone()), Some(val))),
+        Node::Short(path, val) => {
+            if !key.starts_with(&path) {
+                return not_found(Node::Short(path, val));
+            }
+            let (val, found) = proof_to_path(*val, &key[path.len()..], db, allow_non_existent)?;
+            Ok((Node::Short(path, Box::new(val)), found))
+        }
+        Node::Full(mut children) => {
+            let idx = match key.first() {
+                Some(idx) => *idx as usize,
+                None => return not_found(Node::Full(children)),
+            };
+            let child = std::mem::replace(&mut children[idx], Node::Empty);
+            let (child, found) = proof_to_path(child, &key[1..], db, allow_non_existent)?;
+            children[idx] = child;
+            Ok((Node::Full(children), found))
+        }
+    }
+}
+
+fn cmp_prefix(key: &[u8], path: &[u8]) -> core::cmp::Ordering {
+    let end = key.len().min(path.len());
+    key[..end].cmp(path)
+}
+
+// Removes every node strictly between the `left` and `right` paths so the
+// range can be re-inserted. Returns true if the whole node must be dropped.
+pub(crate) fn unset_internal(
+    node: &mut Node,
+    left: &[u8],
+    right: &[u8],
+    pos: usize,
+) -> Result<bool, String> {
+    use core::cmp::Ordering::*;
+    match node {
+        Node::Short(path, val) => {
+            let fork_left = cmp_prefix(&left[pos..], path);
+            let fork_right = cmp_prefix(&right[pos..], path);
+            match (fork_left, fork_right) {
+                (Equal, Equal) => {
+                    let pos = pos + path.len();
+                    if unset_internal(val, left, right, pos)? {
+                        **val = Node::Empty;
+                    }
+                    Ok(false)
+                }
+                (Less, Less) | (Greater, Greater) => Err("empty range".into()),
+                (Less, Greater) => Ok(true),
+                (Equal, _) => {
+                    if let Node::Value(_) = **val {
+                        return Ok(true);
+                    }
+                    let pos = pos + path.len();
+                    unset(val, left, pos, false)?;
+                    Ok(false)
+                }
+                (_, Equal) => {
+                    if let Node::Value(_) = **val {
+                        return Ok(true);
+                    }
+                    let pos = pos + path.len();
+                    unset(val, right, pos, true)?;
+                    Ok(false)
+                }
+                _ => Err("invalid range".into()),
+            }
+        }
+        Node::Full(children) => {
+            let (l, r) = (left[pos] as usize, right[pos] as usize);
+            if l == r && children[l] != Node::Empty {
+                if unset_internal(&mut children[l], left, right, pos + 1)? {
+                    children[l] = Node::Empty;
+                }
+                return Ok(false);
+            }
+            for child in children.iter_mut().take(r).skip(l + 1) {
+                *child = Node::Empty;
+            }
+            if unset(&mut children[l], left, pos + 1, false)? {
+                children[l] = Node::Empty;
+            }
+            if unset(&mut children[r], right, pos + 1, true)? {
+                children[r] = Node::Empty;
+            }
+            Ok(false)
+        }
+        _ => Err("invalid fork point".into()),
+    }
+}
+
+// Removes everything on one side of `key` below `node`: the left side when
+// `remove_left` is set, otherwise the right side.
+fn unset(node: &mut Node, key: &[u8], pos: usize, remove_left: bool) -> Result<bool, String> {
+    match node {
+        Node::Full(children) => {
+            let idx = key[pos] as usize;
+            let range = match remove_left {
+                true => 0..idx,
+                false => idx + 1..16,
+            };
+            for i in range {
+                children[i] = Node::Empty;
+            }
+            if unset(&mut children[idx], key, pos + 1, remove_left)? {
+                children[idx] = Node::Empty;
+            }
+            Ok(false)
+        }
+        Node::Short(path, val) => {
+            let rest = &key[pos..];
+            if !rest.starts_with(path) {
+                // the path forks off here; drop the subtree only if it
+                // lies inside the range
+                let ord = path.as_slice().cmp(rest);
+                return Ok(match remove_left {
+                    true => ord == core::cmp::Ordering::Less,
+                    false => ord == core::cmp::Ordering::Greater,
+                });
+            }
+            if let Node::Value(_) = **val {
+                return Ok(true);
+            }
+            let pos = pos + path.len();
+            unset(val, key, pos, remove_left)?;
+            Ok(false)
+        }
+        Node::Empty => Ok(false),
+        _ => Err("unexpected node on the proof path".into()),
+    }
+}
+
+// Collects the nodes a proof for `key` consists of, root first.
+#[cfg(test)]
+pub(crate) fn prove(node: &Node, key: &[u8]) -> Vec<Vec<u8>> {
+    let mut proof = vec![encode_node(node)];
+    let (mut node, mut key) = (node, key);
+    loop {
+        node = match node {
+            Node::Short(path, val) if key.starts_with(path) => {
+                key = &key[path.len()..];
+                val
+            }
+            Node::Full(children) => {
+                let child = &children[key[0] as usize];
+                key = &key[1..];
+                child
+            }
+            _ => return proof,
+        };
+        let enc = encode_node(node);
+        match node {
+            Node::Short(..) | Node::Full(..) if enc.len() >= 32 => proof.push(enc),
+            _ => {}
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{KeccakHasher, SU256};
+
+    fn entries(n: u64) -> Vec<(Vec<u8>, Vec<u8>)> {
+        let mut entries: Vec<_> = (0..n)
+            .map(|i| {
+                let key = keccak_hash(&i.to_be_bytes()).to_vec();
+                (key, rlp::encode(&SU256::from(i + 1)).to_vec())
+            })
+            .collect();
+        entries.sort();
+        entries
+    }
+
+    fn build(entries: &[(Vec<u8>, Vec<u8>)]) -> Node {
+        let mut root = Node::Empty;
+        for (key, val) in entries {
+            root = insert(root, &key_to_nibbles(key), val.clone()).unwrap();
+        }
+        root
+    }
+
+    #[test]
+    fn test_root_matches_triehash() {
+        for n in [0, 1, 2, 3, 16, 17, 100] {
+            let entries = entries(n);
+            let want: SH256 = triehash::trie_root::<KeccakHasher, _, _, _>(entries.clone()).into();
+            assert_eq!(root_hash(&build(&entries)), want, "{} entries", n);
+        }
+    }
+
+    #[test]
+    fn test_compact_roundtrip() {
+        for nibbles in [vec![], vec![1], vec![1, 2], vec![1, 2, 16], vec![3, 16]] {
+            let (got, leaf) = compact_decode(&compact_encode(&nibbles)).unwrap();
+            assert_eq!(got, nibbles);
+            assert_eq!(leaf, nibbles.last() == Some(&TERMINATOR));
+        }
+    }
+
+    #[test]
+    fn test_decode_node_roundtrip() {
+        let root = build(&entries(50));
+        let key = key_to_nibbles(&entries(50)[7].0);
+        for node in prove(&root, &key) {
+            assert_eq!(encode_node(&decode_node(&node).unwrap()), node);
+        }
+    }
+
+    #[test]
+    fn test_proof_to_path() {
+        let entries = entries(50);
+        let root = build(&entries);
+        let key = key_to_nibbles(&entries[7].0);
+        let db = proof_db(&prove(&root, &key));
+
+        let (_, val) = proof_to_path(Node::Hash(root_hash(&root)), &key, &db, false).unwrap();
+        assert_eq!(val, Some(entries[7].1.clone()));
+
+        let missing = key_to_nibbles(&[0_u8; 32]);
+        assert!(proof_to_path(Node::Hash(root_hash(&root)), &missing, &db, false).is_err());
+    }
+}
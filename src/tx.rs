@@ -1,8 +1,11 @@
 use std::prelude::v1::*;
 
-use crate::Hasher;
+use crate::{Hasher, MethodRegistry};
 
-use super::{BlockHeader, Nilable, Signer, SH160, SH256, SU256, SU64};
+use super::{
+    create_address, legacy_chain_id, BlockHeader, Fork, Nilable, Signer, SignerError, SH160, SH256,
+    SU256, SU64,
+};
 use crypto::{
     keccak_hash, secp256k1_rec_sign_bytes, Secp256k1PrivateKey, Secp256k1RecoverableSignature,
 };
@@ -77,12 +80,52 @@ impl TransactionAccessTuple {
     }
 }
 
+// Parses the RPC object form: `{"address": "0x..", "storageKeys": ["0x..", ..]}`
+impl core::convert::TryFrom<&Value> for TransactionAccessTuple {
+    type Error = String;
+    fn try_from(val: &Value) -> Result<Self, Self::Error> {
+        fn parse_hex(val: &Value, len: usize) -> Result<Vec<u8>, String> {
+            let s = val
+                .as_str()
+                .ok_or_else(|| format!("expected hex string, got: {}", val))?;
+            let data = hex::decode(s.trim_start_matches("0x"))
+                .map_err(|err| format!("invalid hex {:?}: {:?}", s, err))?;
+            if data.len() != len {
+                return Err(format!(
+                    "invalid length of {:?}, want: {}, got: {}",
+                    s,
+                    len,
+                    data.len()
+                ));
+            }
+            Ok(data)
+        }
+
+        let address = val
+            .get("address")
+            .ok_or_else(|| format!("missing field address"))?;
+        let address = SH160::from_slice(&parse_hex(address, 20)?);
+        let keys = val
+            .get("storageKeys")
+            .and_then(|keys| keys.as_array())
+            .ok_or_else(|| format!("missing field storageKeys"))?;
+        let mut storage_keys = Vec::with_capacity(keys.len());
+        for key in keys {
+            storage_keys.push(SH256::from_slice(&parse_hex(key, 32)?));
+        }
+        Ok(Self {
+            address,
+            storage_keys,
+        })
+    }
+}
+
 impl From<Vec<&str>> for TransactionAccessTuple {
     fn from(list: Vec<&str>) -> Self {
         let address = list[0].into();
         let mut storage_keys = Vec::with_capacity(list.len() - 1);
-        for item in list {
-            storage_keys.push(item.into());
+        for item in &list[1..] {
+            storage_keys.push((*item).into());
         }
         Self {
             address,
@@ -92,29 +135,107 @@ impl From<Vec<&str>> for TransactionAccessTuple {
 }
 
 #[derive(Clone, Debug, Deserialize, Serialize, Default, PartialEq, Eq)]
-#[serde(rename_all = "camelCase")]
+#[serde(rename_all = "camelCase", try_from = "TransactionJson")]
 pub struct Transaction {
+    #[serde(skip_serializing_if = "Option::is_none")]
     pub block_hash: Option<SH256>,
+    #[serde(skip_serializing_if = "Option::is_none")]
     pub block_number: Option<SU64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
     pub from: Option<SH160>,
     pub gas: SU64,
+    #[serde(skip_serializing_if = "Option::is_none")]
     pub gas_price: Option<SU256>,
+    #[serde(skip_serializing_if = "Option::is_none")]
     pub max_fee_per_gas: Option<SU256>,
+    #[serde(skip_serializing_if = "Option::is_none")]
     pub max_priority_fee_per_gas: Option<SU256>,
     pub hash: SH256,
     pub input: HexBytes,
     pub nonce: SU64,
+    // geth always emits `to`, as null for contract creations
     pub to: Option<SH160>,
+    #[serde(skip_serializing_if = "Option::is_none")]
     pub transaction_index: Option<SU64>,
     pub value: SU256,
     pub r#type: SU64,
+    #[serde(skip_serializing_if = "Option::is_none")]
     pub access_list: Option<Vec<TransactionAccessTuple>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
     pub chain_id: Option<SU256>,
+    // some nodes only report `yParity` for typed transactions
+    #[serde(default)]
     pub v: SU256,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub y_parity: Option<SU64>,
     pub r: SU256,
     pub s: SU256,
 }
 
+// The wire form of `Transaction`. Some clients (e.g. Erigon, Besu) name
+// `input` and `type` as `data` and `txType`, and some send both spellings.
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct TransactionJson {
+    block_hash: Option<SH256>,
+    block_number: Option<SU64>,
+    from: Option<SH160>,
+    gas: SU64,
+    gas_price: Option<SU256>,
+    max_fee_per_gas: Option<SU256>,
+    max_priority_fee_per_gas: Option<SU256>,
+    hash: SH256,
+    input: Option<HexBytes>,
+    data: Option<HexBytes>,
+    nonce: SU64,
+    to: Option<SH160>,
+    transaction_index: Option<SU64>,
+    value: SU256,
+    r#type: Option<SU64>,
+    tx_type: Option<SU64>,
+    access_list: Option<Vec<TransactionAccessTuple>>,
+    chain_id: Option<SU256>,
+    #[serde(default)]
+    v: SU256,
+    y_parity: Option<SU64>,
+    r: SU256,
+    s: SU256,
+}
+
+impl core::convert::TryFrom<TransactionJson> for Transaction {
+    type Error = String;
+    fn try_from(tx: TransactionJson) -> Result<Self, Self::Error> {
+        Ok(Self {
+            block_hash: tx.block_hash,
+            block_number: tx.block_number,
+            from: tx.from,
+            gas: tx.gas,
+            gas_price: tx.gas_price,
+            max_fee_per_gas: tx.max_fee_per_gas,
+            max_priority_fee_per_gas: tx.max_priority_fee_per_gas,
+            hash: tx.hash,
+            input: tx
+                .input
+                .or(tx.data)
+                .ok_or_else(|| format!("missing field `input`"))?,
+            nonce: tx.nonce,
+            to: tx.to,
+            transaction_index: tx.transaction_index,
+            value: tx.value,
+            r#type: tx
+                .r#type
+                .or(tx.tx_type)
+                .ok_or_else(|| format!("missing field `type`"))?,
+            access_list: tx.access_list,
+            chain_id: tx.chain_id,
+            v: tx.v,
+            y_parity: tx.y_parity,
+            r: tx.r,
+            s: tx.s,
+        })
+    }
+}
+
 impl rlp::Encodable for TransactionInner {
     fn rlp_append(&self, s: &mut rlp::RlpStream) {
         match self {
@@ -200,6 +321,20 @@ impl TransactionInner {
         rlp::decode(data)
     }
 
+    // Same as `from_bytes`, but rejects any bytes left after the transaction.
+    pub fn from_bytes_strict(data: &[u8]) -> Result<Self, rlp::DecoderError> {
+        if data.len() < 1 {
+            return Err(rlp::DecoderError::RlpIsTooShort);
+        }
+        // typed transactions carry a one byte prefix before the rlp list
+        let payload = if data[0] >= 0xc0 { data } else { &data[1..] };
+        let info = rlp::Rlp::new(payload).payload_info()?;
+        if info.header_len + info.value_len != payload.len() {
+            return Err(rlp::DecoderError::RlpInconsistentLengthAndData);
+        }
+        Self::from_bytes(data)
+    }
+
     pub fn to_transaction(self, header: Option<&BlockHeader>) -> Transaction {
         let mut target = Transaction::default();
         target.hash = self.hash();
@@ -258,6 +393,9 @@ impl TransactionInner {
                 target.s = tx.s;
             }
         }
+        if target.r#type.as_u64() != 0 {
+            target.y_parity = Some(target.v.as_u64().into());
+        }
         if let Some(header) = header {
             target.block_hash = Some(header.hash());
             target.block_number = Some(header.number.as_u64().into());
@@ -265,6 +403,18 @@ impl TransactionInner {
         target
     }
 
+    // The object `eth_getTransactionByHash` would return for this transaction,
+    // `transactionIndex` is omitted as it isn't known here.
+    pub fn to_rpc_json(&self, signer: &Signer, header: Option<&BlockHeader>) -> Value {
+        let mut tx = self.clone().to_transaction(header);
+        tx.from = signer.try_sender(self).ok();
+        if let Self::Legacy(legacy) = self {
+            // the chain id the tx was signed for, which may not be the signer's
+            tx.chain_id = legacy_chain_id(&legacy.v).ok().flatten();
+        }
+        serde_json::to_value(&tx).unwrap()
+    }
+
     pub fn value(&self) -> SU256 {
         match self {
             Self::Legacy(tx) => tx.value.clone(),
@@ -293,12 +443,9 @@ impl TransactionInner {
         match self {
             Self::Legacy(tx) => tx.gas_price,
             Self::AccessList(tx) => tx.gas_price,
-            Self::DynamicFee(tx) => match base_fee {
-                Some(base_fee) => tx
-                    .max_fee_per_gas
-                    .min(base_fee + &tx.max_priority_fee_per_gas),
-                None => tx.max_fee_per_gas,
-            },
+            Self::DynamicFee(tx) => {
+                capped_gas_price(&tx.max_fee_per_gas, &tx.max_priority_fee_per_gas, base_fee)
+            }
         }
     }
 
@@ -309,6 +456,19 @@ impl TransactionInner {
         (gas * gas_price) + value
     }
 
+    pub fn try_cost(&self, base_fee: Option<SU256>) -> Result<SU256, String> {
+        let gas: SU256 = self.gas().into();
+        let gas_price = self.gas_price(base_fee);
+        let fee = gas
+            .raw()
+            .checked_mul(*gas_price.raw())
+            .ok_or_else(|| format!("gas * gas_price overflow: {} * {}", gas, gas_price))?;
+        let cost = fee
+            .checked_add(*self.value().raw())
+            .ok_or_else(|| format!("fee + value overflow: {} + {}", fee, self.value()))?;
+        Ok(cost.into())
+    }
+
     pub fn access_list(&self) -> Option<&[TransactionAccessTuple]> {
         match self {
             Self::Legacy(_) => None,
@@ -325,6 +485,15 @@ impl TransactionInner {
         }
     }
 
+    pub fn is_underpriced(&self, base_fee: &SU256) -> bool {
+        self.max_fee_per_gas() < base_fee
+    }
+
+    pub fn tip_range(&self, base_fee: &SU256) -> (SU256, SU256) {
+        let effective_tip = self.effective_gas_tip(Some(base_fee)).unwrap_or_default();
+        (effective_tip, self.max_priority_fee_per_gas().clone())
+    }
+
     pub fn reward(&self, gas: u64, base_fee: Option<&SU256>) -> Option<SU256> {
         self.effective_gas_tip(base_fee)
             .map(|item| item * SU256::from(gas))
@@ -362,6 +531,47 @@ impl TransactionInner {
         }
     }
 
+    // The earliest fork able to include this transaction.
+    pub fn min_fork(&self) -> Fork {
+        match self {
+            Self::Legacy(tx) => match tx.v.as_u64() {
+                0 | 1 | 27 | 28 => Fork::Frontier,
+                // EIP-155 replay protection
+                _protected => Fork::SpuriousDragon,
+            },
+            Self::AccessList(_) => Fork::Berlin,
+            Self::DynamicFee(_) => Fork::London,
+        }
+    }
+
+    pub fn method_id(&self) -> Option<[u8; 4]> {
+        if self.to().is_none() {
+            return None;
+        }
+        let input = self.input();
+        if input.len() < 4 {
+            return None;
+        }
+        let mut id = [0_u8; 4];
+        id.copy_from_slice(&input[..4]);
+        Some(id)
+    }
+
+    pub fn method_name<'a, R: MethodRegistry>(&self, registry: &'a R) -> Option<&'a str> {
+        registry.name(&self.method_id()?)
+    }
+
+    pub fn is_simple_transfer(&self) -> bool {
+        self.to().is_some() && self.input().len() == 0
+    }
+
+    pub fn created_contract(&self, sender: &SH160) -> Option<SH160> {
+        match self.to() {
+            Some(_) => None,
+            None => Some(create_address(sender, self.nonce())),
+        }
+    }
+
     pub fn sender(&self, signer: &Signer) -> SH160 {
         signer.sender(self)
     }
@@ -393,6 +603,14 @@ impl TransactionInner {
         }
     }
 
+    pub fn y_parity(&self) -> Option<u8> {
+        match self {
+            Self::Legacy(_) => None,
+            Self::AccessList(tx) => Some(tx.v.as_u32() as u8),
+            Self::DynamicFee(tx) => Some(tx.v.as_u32() as u8),
+        }
+    }
+
     pub fn ty(&self) -> u64 {
         match self {
             Self::Legacy(_) => 0,
@@ -401,6 +619,123 @@ impl TransactionInner {
         }
     }
 
+    pub fn intrinsic_gas(&self, fork: Fork) -> u64 {
+        let is_create = self.to().is_none();
+        let mut gas: u64 = if is_create && fork >= Fork::Homestead {
+            53000
+        } else {
+            21000
+        };
+
+        let input = self.input();
+        let zero_bytes = input.iter().filter(|b| **b == 0).count() as u64;
+        let non_zero_bytes = input.len() as u64 - zero_bytes;
+        // EIP-2028
+        let non_zero_gas = if fork >= Fork::Istanbul { 16 } else { 68 };
+        gas = gas
+            .saturating_add(zero_bytes.saturating_mul(4))
+            .saturating_add(non_zero_bytes.saturating_mul(non_zero_gas));
+        // EIP-3860
+        if is_create && fork >= Fork::Shanghai {
+            let words = (input.len() as u64 + 31) / 32;
+            gas = gas.saturating_add(words.saturating_mul(2));
+        }
+
+        if let Some(access_list) = self.access_list() {
+            for item in access_list {
+                gas = gas
+                    .saturating_add(2400)
+                    .saturating_add((item.storage_keys.len() as u64).saturating_mul(1900));
+            }
+        }
+        gas
+    }
+
+    // Cheap stateless checks for a mempool front door, returns the sender.
+    pub fn basic_validate(
+        &self,
+        signer: &Signer,
+        opts: ValidateOpts,
+    ) -> Result<SH160, ValidateError> {
+        let min_fork = self.min_fork();
+        if min_fork > opts.fork {
+            return Err(ValidateError::UnsupportedType {
+                ty: self.ty(),
+                fork: opts.fork,
+            });
+        }
+
+        let gas = self.gas().as_u64();
+        if let Some(max_gas_limit) = opts.max_gas_limit {
+            if gas > max_gas_limit {
+                return Err(ValidateError::GasLimitExceeded {
+                    max: max_gas_limit,
+                    got: gas,
+                });
+            }
+        }
+        let intrinsic_gas = self.intrinsic_gas(opts.fork);
+        if gas < intrinsic_gas {
+            return Err(ValidateError::IntrinsicGasTooLow {
+                want: intrinsic_gas,
+                got: gas,
+            });
+        }
+        if self.max_fee_per_gas() < self.max_priority_fee_per_gas() {
+            return Err(ValidateError::TipAboveFeeCap);
+        }
+
+        // also rejects a legacy v that doesn't match the signer's chain
+        let sender = signer.try_sender(self).map_err(ValidateError::Signer)?;
+        // EIP-2
+        let sig = self.signature(signer.chain_id.as_u64());
+        if opts.fork >= Fork::Homestead && SU256::from_big_endian(&sig.s) > secp256k1_half_n() {
+            return Err(ValidateError::HighS);
+        }
+        Ok(sender)
+    }
+
+    // Catches degenerate transactions before a signature is wasted on them.
+    pub fn pre_sign_check(&self) -> Result<(), String> {
+        if self.gas().as_u64() == 0 {
+            return Err(format!("gas limit is zero"));
+        }
+        // EIP-2681
+        if self.nonce() == u64::MAX {
+            return Err(format!("nonce overflow: {}", self.nonce()));
+        }
+        match self {
+            Self::Legacy(tx) => {
+                if tx.gas_price.is_zero() {
+                    return Err(format!("gas price is zero"));
+                }
+            }
+            Self::AccessList(tx) => {
+                if tx.gas_price.is_zero() {
+                    return Err(format!("gas price is zero"));
+                }
+                if tx.chain_id.is_zero() {
+                    return Err(format!("chain id is zero"));
+                }
+            }
+            Self::DynamicFee(tx) => {
+                if tx.max_fee_per_gas.is_zero() {
+                    return Err(format!("max fee per gas is zero"));
+                }
+                if tx.max_fee_per_gas < tx.max_priority_fee_per_gas {
+                    return Err(format!(
+                        "max priority fee per gas higher than max fee per gas: {} > {}",
+                        tx.max_priority_fee_per_gas, tx.max_fee_per_gas
+                    ));
+                }
+                if tx.chain_id.is_zero() {
+                    return Err(format!("chain id is zero"));
+                }
+            }
+        }
+        Ok(())
+    }
+
     pub fn sign(&mut self, prvkey: &Secp256k1PrivateKey, chain_id: u64) {
         let mut trim_suffix = 0;
         match self {
@@ -429,19 +764,26 @@ impl TransactionInner {
             signed_txn_bytes.truncate(signed_txn_bytes.len() - trim_suffix as usize);
         }
         let rec_sig = secp256k1_rec_sign_bytes(prvkey, &signed_txn_bytes);
+        self.with_signature(rec_sig, chain_id);
+    }
+
+    // Writes a signature produced elsewhere (e.g. over `signing_hash`), where
+    // `sig.v` is the raw recovery id.
+    pub fn with_signature(&mut self, rec_sig: Secp256k1RecoverableSignature, chain_id: u64) {
+        let v = encode_v(rec_sig.v, self.ty() as u8, chain_id);
         match self {
             Self::Legacy(tx) => {
-                tx.v = (u64::from(rec_sig.v) + chain_id * 2 + 35).into();
+                tx.v = v;
                 tx.r = rec_sig.r.into();
                 tx.s = rec_sig.s.into();
             }
             Self::DynamicFee(tx) => {
-                tx.v = u64::from(rec_sig.v).into();
+                tx.v = v;
                 tx.r = rec_sig.r.into();
                 tx.s = rec_sig.s.into();
             }
             Self::AccessList(tx) => {
-                tx.v = u64::from(rec_sig.v).into();
+                tx.v = v;
                 tx.r = rec_sig.r.into();
                 tx.s = rec_sig.s.into();
             }
@@ -452,6 +794,11 @@ impl TransactionInner {
         Hasher::hash(self)
     }
 
+    // The digest a signer operates on, e.g. for signing with an external HSM.
+    pub fn signing_hash(&self, chain_id: &SU256) -> SH256 {
+        keccak_hash(&self.sign_msg(chain_id)).into()
+    }
+
     pub fn sign_msg(&self, chain_id: &SU256) -> Vec<u8> {
         let data = match self {
             TransactionInner::DynamicFee(tx) => {
@@ -516,6 +863,118 @@ impl TransactionInner {
     }
 }
 
+// Classifies a raw EIP-2718 transaction without decoding it. Blob (3) and
+// set-code (4) transactions are recognized here even though this crate can't
+// decode them, so they can still be routed.
+pub fn peek_tx_type(data: &[u8]) -> Result<u8, rlp::DecoderError> {
+    if data.len() < 1 {
+        return Err(rlp::DecoderError::RlpIsTooShort);
+    }
+    match data[0] {
+        0xc0..=0xff => Ok(0),
+        ty @ 1..=4 => Ok(ty),
+        _ => Err(rlp::DecoderError::Custom("unknown tx prefix")),
+    }
+}
+
+// Packs transactions into one rlp list the way a block body does: legacy
+// transactions inline, typed ones wrapped as rlp strings.
+pub fn pack_transactions(txs: &[TransactionInner]) -> Vec<u8> {
+    let mut s = rlp::RlpStream::new_list(txs.len());
+    for tx in txs {
+        let data = tx.to_bytes();
+        match tx {
+            TransactionInner::Legacy(_) => {
+                s.append_raw(&data, 1);
+            }
+            _ => {
+                s.append(&data);
+            }
+        }
+    }
+    s.out().to_vec()
+}
+
+pub fn unpack_transactions(data: &[u8]) -> Result<Vec<TransactionInner>, rlp::DecoderError> {
+    let list = rlp::Rlp::new(data);
+    let info = list.payload_info()?;
+    if info.header_len + info.value_len != data.len() {
+        return Err(rlp::DecoderError::RlpInconsistentLengthAndData);
+    }
+    let count = list.item_count()?;
+    let mut txs = Vec::with_capacity(count);
+    for i in 0..count {
+        // `iter` would stop silently at a malformed item
+        let item = list.at(i)?;
+        let tx = if item.is_list() {
+            TransactionInner::from_bytes(item.as_raw())?
+        } else {
+            let data: Vec<u8> = item.as_val()?;
+            TransactionInner::from_bytes(&data)?
+        };
+        txs.push(tx);
+    }
+    Ok(txs)
+}
+
+pub fn decode_and_recover(
+    data: &[u8],
+    signer: &Signer,
+) -> Result<(TransactionInner, SH160), String> {
+    let tx =
+        TransactionInner::from_bytes(data).map_err(|err| format!("decode tx failed: {:?}", err))?;
+    let sender = signer
+        .try_sender(&tx)
+        .map_err(|err| format!("recover sender failed: {:?}", err))?;
+    Ok((tx, sender))
+}
+
+// min(max_fee, base_fee + tip), where base_fee + tip may overflow, in which
+// case the fee cap applies.
+pub(crate) fn capped_gas_price(max_fee: &SU256, tip: &SU256, base_fee: Option<SU256>) -> SU256 {
+    match base_fee {
+        Some(base_fee) => match base_fee.raw().checked_add(*tip.raw()) {
+            Some(price) => (*max_fee).min(price.into()),
+            None => *max_fee,
+        },
+        None => *max_fee,
+    }
+}
+
+pub fn suggest_fees(base_fee: &SU256, priority_fee: &SU256) -> (SU256, SU256) {
+    let two: SU256 = 2u64.into();
+    let max_fee_per_gas = base_fee * &two + priority_fee;
+    (max_fee_per_gas, priority_fee.clone())
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct ValidateOpts {
+    pub fork: Fork,
+    pub max_gas_limit: Option<u64>,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum ValidateError {
+    UnsupportedType { ty: u64, fork: Fork },
+    GasLimitExceeded { max: u64, got: u64 },
+    IntrinsicGasTooLow { want: u64, got: u64 },
+    TipAboveFeeCap,
+    HighS,
+    Signer(SignerError),
+}
+
+fn secp256k1_half_n() -> SU256 {
+    "0x7fffffffffffffffffffffffffffffff5d576e7357a4501ddfe92f46681b20a0".into()
+}
+
+pub fn encode_v(recid: u8, tx_type: u8, chain_id: u64) -> SU256 {
+    match tx_type {
+        // EIP-155 protected legacy transaction
+        0 => (u64::from(recid) + chain_id * 2 + 35).into(),
+        _ => u64::from(recid).into(),
+    }
+}
+
 impl Hasher for TransactionInner {
     fn hash(&self) -> SH256 {
         let data = rlp::encode(self).to_vec();
@@ -526,7 +985,28 @@ impl Hasher for TransactionInner {
 }
 
 impl Transaction {
+    pub fn sort_by_index(txs: &mut [Transaction]) {
+        // transactions without an index (pending) go last
+        txs.sort_by_key(|tx| (tx.transaction_index.is_none(), tx.transaction_index));
+    }
+
+    // geth omits the inclusion fields of pending transactions instead of
+    // returning them as null.
+    pub fn to_pending_json(&self) -> Value {
+        let mut val = serde_json::to_value(self).unwrap();
+        if let Value::Object(map) = &mut val {
+            map.remove("blockHash");
+            map.remove("blockNumber");
+            map.remove("transactionIndex");
+        }
+        val
+    }
+
     pub fn inner(self) -> Option<TransactionInner> {
+        let parity = match self.y_parity {
+            Some(y_parity) => y_parity.as_u256(),
+            None => self.v,
+        };
         Some(match self.r#type.as_u64() {
             0 => TransactionInner::Legacy(LegacyTx {
                 nonce: self.nonce,
@@ -548,7 +1028,7 @@ impl Transaction {
                 value: self.value,
                 data: self.input,
                 access_list: self.access_list?,
-                v: self.v,
+                v: parity,
                 r: self.r,
                 s: self.s,
             }),
@@ -562,7 +1042,7 @@ impl Transaction {
                 to: self.to.into(),
                 value: self.value,
                 data: self.input,
-                v: self.v,
+                v: parity,
                 r: self.r,
                 s: self.s,
             }),
@@ -571,6 +1051,15 @@ impl Transaction {
     }
 }
 
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum ConversionError {
+    // the fields required by the transaction type are missing
+    MissingFields,
+    // the `hash` field doesn't match the hash of the decoded fields
+    HashMismatch { want: SH256, got: SH256 },
+    Signer(SignerError),
+}
+
 #[derive(Debug, Clone, Eq, PartialEq)]
 pub struct PoolTx {
     pub caller: SH160,
@@ -588,6 +1077,21 @@ impl PoolTx {
         Self::with_acl(signer, tx, Vec::new(), 0, 0, "".into(), true)
     }
 
+    // Like `with_tx`, but reports an unrecoverable sender instead of panicking.
+    pub fn try_with_tx(signer: &Signer, tx: TransactionInner) -> Result<Self, SignerError> {
+        let caller = signer.try_sender(&tx)?;
+        Ok(Self {
+            caller,
+            hash: tx.hash(),
+            tx: Arc::new(tx),
+            access_list: Arc::new(Vec::new()),
+            gas: 0,
+            allow_revert: true,
+            block: 0,
+            result: "".into(),
+        })
+    }
+
     pub fn with_acl(
         signer: &Signer,
         tx: TransactionInner,
@@ -611,18 +1115,49 @@ impl PoolTx {
         }
     }
 
+    // Like `with_tx`, but trusts the `from` of an RPC transaction when present
+    // instead of recovering the sender again.
+    pub fn from_rpc_transaction(tx: Transaction, signer: &Signer) -> Result<Self, ConversionError> {
+        let from = tx.from;
+        let want = tx.hash;
+        let tx = tx.inner().ok_or(ConversionError::MissingFields)?;
+        let hash = tx.hash();
+        if hash != want {
+            return Err(ConversionError::HashMismatch { want, got: hash });
+        }
+        let caller = match from {
+            Some(from) => from,
+            None => signer.try_sender(&tx).map_err(ConversionError::Signer)?,
+        };
+        Ok(Self {
+            caller,
+            tx: Arc::new(tx),
+            access_list: Arc::new(Vec::new()),
+            hash,
+            gas: 0,
+            allow_revert: true,
+            block: 0,
+            result: "".into(),
+        })
+    }
+
     pub fn from_rlp(signer: &Signer, tx: PoolTxRlp) -> Result<Self, rlp::DecoderError> {
         let access_list = rlp::Rlp::new(&tx.access_list).as_list()?;
-        let inner = rlp::decode(&tx.tx)?;
-        Ok(Self::with_acl(
-            signer,
-            inner,
-            access_list,
-            tx.gas,
-            tx.blk,
-            tx.result,
-            tx.allow_revert,
-        ))
+        let inner: TransactionInner = rlp::decode(&tx.tx)?;
+        // the bytes may come from an untrusted peer, `with_acl` would panic
+        let caller = signer
+            .try_sender(&inner)
+            .map_err(|_| rlp::DecoderError::Custom("invalid tx signature"))?;
+        Ok(Self {
+            caller,
+            hash: inner.hash(),
+            tx: Arc::new(inner),
+            access_list: Arc::new(access_list),
+            gas: tx.gas,
+            allow_revert: tx.allow_revert,
+            block: tx.blk,
+            result: tx.result,
+        })
     }
 
     pub fn from_bytes(signer: &Signer, data: &[u8]) -> Result<Self, rlp::DecoderError> {
@@ -711,6 +1246,32 @@ pub trait TxTrait: Clone + std::fmt::Debug + Serialize {
             }
         }
     }
+
+    // geth's priceBump rule: a replacement with the same nonce must raise both
+    // the fee cap and the tip by at least `min_bump_percent`. The caller is
+    // responsible for checking that both come from the same sender.
+    fn is_replacement_of(&self, old: &impl TxTrait, min_bump_percent: u64) -> bool {
+        if self.nonce() != old.nonce() {
+            return false;
+        }
+        let threshold = |price: &SU256| -> Option<SU256> {
+            let bump: SU256 = price
+                .raw()
+                .checked_mul(*SU256::from(min_bump_percent).raw())?
+                .into();
+            let bump = bump / SU256::from(100_u64);
+            Some(price.raw().checked_add(*bump.raw())?.into())
+        };
+        let bumped = |new: &SU256, old: &SU256| match threshold(old) {
+            Some(threshold) => new > old && new >= &threshold,
+            None => false,
+        };
+        bumped(self.max_fee_per_gas(), old.max_fee_per_gas())
+            && bumped(
+                self.max_priority_fee_per_gas(),
+                old.max_priority_fee_per_gas(),
+            )
+    }
 }
 
 impl TxTrait for TransactionInner {
@@ -804,3 +1365,880 @@ impl TxTrait for TransactionInner {
 
 //     }
 // }
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+
+    // EIP-155 example, signed with the private key 0x4646..46.
+    pub(crate) const LEGACY_TX: &str = "0xf86c098504a817c800825208943535353535353535353535353535353535353535880de0b6b3a76400008025a028ef61340bd939bc2195fe537567866003e1a15d3c71ff63e1590620aa636276a067cbe9d8997f761aecb703304b3800ccf555c9f3dc64214b297fb1966a3b6d83";
+    pub(crate) const ACCESS_LIST_TX: &str = "0x01f8a501018506fc23ac0082c3509435353535353535353535353535353535353535358203e884a9059cbbf838f7943535353535353535353535353535353535353535e1a0000000000000000000000000000000000000000000000000000000000000000001a0904af234e90a1c688e0a41982cf521ec1de8e160ff533c89cac4baeb67f5c580a07ed6d7784b8c3897b34f18402d1f7ef254691250592bc972921d4957d586244e";
+    pub(crate) const DYNAMIC_FEE_TX: &str = "0x02f86b0102847735940085174876e8008252089435353535353535353535353535353535353535350180c080a0ab6d3920f1b94cb05a46e3d5a41cdc0648b8cd21ec625a17e99e7bfe1e184a39a073928c1bbd20231b5e8c575a9023fe10626e28bb49326a24218561137e460d95";
+    // pre-EIP-155 legacy tx with v = 27
+    pub(crate) const UNPROTECTED_TX: &str = "0xf863808504a817c80082520894353535353535353535353535353535353535353501801b9fe62f281bcf123651cd5b54c00fc951b75510c85ec7ed53bd1a5c7ac5956f8ca01bb62b1861b33ceb7a08adee5413ba322525af77cc57a4c0d51b020ffbbffb9c";
+    // contract creation with nonce 3
+    pub(crate) const CREATE_TX: &str = "0xf856038504a817c800830186a080808560006000f325a0e545b2f53b2e5679b0a41ce879d132c636b3a309059affc45b74a3ae43f6bd54a054b164e3b1c5887357bd74759e41c1bbaa1c21de2d828ad7e012ee0e38f15d0a";
+    pub(crate) const SENDER: &str = "0x9d8a62f656a8d1615c1294fd71e9cfb3e4855a4f";
+
+    pub(crate) fn decode_tx(raw: &str) -> TransactionInner {
+        TransactionInner::from_bytes(&hex::decode(&raw[2..]).unwrap()).unwrap()
+    }
+
+    pub(crate) fn signer() -> Signer {
+        Signer::new(SU256::from(1_u64))
+    }
+
+    #[test]
+    fn test_recover_vectors() {
+        let signer = signer();
+        for raw in [LEGACY_TX, UNPROTECTED_TX, ACCESS_LIST_TX, DYNAMIC_FEE_TX] {
+            let tx = decode_tx(raw);
+            assert_eq!(signer.try_sender(&tx), Ok(SENDER.into()));
+            assert_eq!(HexBytes::from(tx.to_bytes()).to_string(), raw);
+        }
+        assert_eq!(
+            decode_tx(LEGACY_TX).hash(),
+            "0x33469b22e9f636356c4160a87eb19df52b7412e8eac32a4a55ffe88ea8350788".into()
+        );
+    }
+
+    #[test]
+    fn test_transaction_json_aliases() {
+        let tx = decode_tx(LEGACY_TX).to_transaction(None);
+        let json = serde_json::to_value(&tx).unwrap();
+        let input = json["input"].clone();
+        let ty = json["type"].clone();
+
+        let mut data_only = json.clone();
+        let obj = data_only.as_object_mut().unwrap();
+        obj.remove("input");
+        obj.remove("type");
+        obj.insert("data".into(), input.clone());
+        obj.insert("txType".into(), ty.clone());
+        let got: Transaction = serde_json::from_value(data_only.clone()).unwrap();
+        assert_eq!(got, tx);
+
+        let mut both = data_only;
+        let obj = both.as_object_mut().unwrap();
+        obj.insert("input".into(), input);
+        obj.insert("type".into(), ty);
+        let got: Transaction = serde_json::from_value(both).unwrap();
+        assert_eq!(got, tx);
+        assert_eq!(got.inner(), Some(decode_tx(LEGACY_TX)));
+
+        let mut missing = json;
+        missing.as_object_mut().unwrap().remove("input");
+        assert!(serde_json::from_value::<Transaction>(missing).is_err());
+    }
+
+    #[test]
+    fn test_encode_v() {
+        for recid in [0_u8, 1] {
+            for chain_id in [1_u64, 5, 137] {
+                assert_eq!(
+                    encode_v(recid, 0, chain_id),
+                    SU256::from(u64::from(recid) + chain_id * 2 + 35)
+                );
+                for ty in [1_u8, 2] {
+                    assert_eq!(encode_v(recid, ty, chain_id), SU256::from(u64::from(recid)));
+                }
+            }
+        }
+        // matches the EIP-155 example, recovery id 0 on mainnet
+        match decode_tx(LEGACY_TX) {
+            TransactionInner::Legacy(tx) => assert_eq!(tx.v, encode_v(0, 0, 1)),
+            _ => unreachable!(),
+        }
+    }
+
+    #[test]
+    fn test_decode_and_recover() {
+        let raw = hex::decode(&LEGACY_TX[2..]).unwrap();
+        let (tx, sender) = decode_and_recover(&raw, &signer()).unwrap();
+        assert_eq!(tx, decode_tx(LEGACY_TX));
+        assert_eq!(sender, SENDER.into());
+
+        let raw = hex::decode(&DYNAMIC_FEE_TX[2..]).unwrap();
+        assert!(decode_and_recover(&raw, &Signer::new(5_u64.into())).is_err());
+        assert!(decode_and_recover(&raw[..raw.len() - 1], &signer()).is_err());
+        assert!(decode_and_recover(&[], &signer()).is_err());
+    }
+
+    #[test]
+    fn test_suggest_fees() {
+        let gwei = |n: u64| SU256::from(n * 1_000_000_000);
+        assert_eq!(suggest_fees(&gwei(30), &gwei(2)), (gwei(62), gwei(2)));
+        assert_eq!(suggest_fees(&SU256::zero(), &gwei(1)), (gwei(1), gwei(1)));
+        assert_eq!(
+            suggest_fees(&SU256::zero(), &SU256::zero()),
+            (SU256::zero(), SU256::zero())
+        );
+    }
+
+    #[test]
+    fn test_is_underpriced() {
+        let gwei = |n: u64| SU256::from(n * 1_000_000_000);
+        // max fee of 100 gwei
+        let tx = decode_tx(DYNAMIC_FEE_TX);
+        assert!(!tx.is_underpriced(&gwei(30)));
+        assert!(!tx.is_underpriced(&gwei(100)));
+        assert!(tx.is_underpriced(&gwei(101)));
+        // gas price of 20 gwei
+        let tx = decode_tx(LEGACY_TX);
+        assert!(!tx.is_underpriced(&gwei(20)));
+        assert!(tx.is_underpriced(&gwei(21)));
+    }
+
+    #[test]
+    fn test_tip_range() {
+        let gwei = |n: u64| SU256::from(n * 1_000_000_000);
+        // tip of 2 gwei, max fee of 100 gwei
+        let tx = decode_tx(DYNAMIC_FEE_TX);
+        assert_eq!(tx.tip_range(&gwei(50)), (gwei(2), gwei(2)));
+        assert_eq!(tx.tip_range(&gwei(99)), (gwei(1), gwei(2)));
+        assert_eq!(tx.tip_range(&gwei(100)), (SU256::zero(), gwei(2)));
+        assert_eq!(tx.tip_range(&gwei(101)), (SU256::zero(), gwei(2)));
+    }
+
+    #[test]
+    fn test_sort_by_index() {
+        let tx = |index: Option<u64>| {
+            let mut tx = decode_tx(LEGACY_TX).to_transaction(None);
+            tx.transaction_index = index.map(SU64::from);
+            tx
+        };
+        let mut txs = vec![tx(Some(2)), tx(None), tx(Some(0)), tx(Some(3)), tx(Some(1))];
+        Transaction::sort_by_index(&mut txs);
+        let indices: Vec<_> = txs
+            .iter()
+            .map(|tx| tx.transaction_index.map(|n| n.as_u64()))
+            .collect();
+        assert_eq!(indices, vec![Some(0), Some(1), Some(2), Some(3), None]);
+    }
+
+    #[test]
+    fn test_is_simple_transfer() {
+        assert!(decode_tx(LEGACY_TX).is_simple_transfer());
+        assert!(decode_tx(DYNAMIC_FEE_TX).is_simple_transfer());
+        // calls `transfer(address,uint256)`
+        assert!(!decode_tx(ACCESS_LIST_TX).is_simple_transfer());
+        assert!(!decode_tx(CREATE_TX).is_simple_transfer());
+    }
+
+    #[test]
+    fn test_try_cost() {
+        let max = SU256::from(crate::U256::max_value());
+        let gwei = |n: u64| SU256::from(n * 1_000_000_000);
+
+        let tx = decode_tx(LEGACY_TX);
+        assert_eq!(tx.try_cost(None), Ok(tx.cost(None)));
+
+        let mut expensive = tx.clone();
+        if let TransactionInner::Legacy(tx) = &mut expensive {
+            tx.gas_price = max;
+        }
+        let err = expensive.try_cost(None).unwrap_err();
+        assert!(err.starts_with("gas * gas_price overflow"), "{}", err);
+
+        let mut rich = tx;
+        if let TransactionInner::Legacy(tx) = &mut rich {
+            tx.value = max;
+        }
+        let err = rich.try_cost(None).unwrap_err();
+        assert!(err.starts_with("fee + value overflow"), "{}", err);
+
+        // the fee cap applies when base fee + tip overflows
+        let tx = decode_tx(DYNAMIC_FEE_TX);
+        let capped = SU256::from(21000_u64) * gwei(100) + SU256::from(1_u64);
+        assert_eq!(tx.try_cost(Some(max)), Ok(capped));
+        assert_eq!(tx.try_cost(Some(gwei(100))), Ok(capped));
+        assert_eq!(tx.try_cost(Some(gwei(30))), Ok(tx.cost(Some(gwei(30)))));
+    }
+
+    #[test]
+    fn test_from_bytes_strict() {
+        for raw in [LEGACY_TX, ACCESS_LIST_TX, DYNAMIC_FEE_TX] {
+            let mut data = hex::decode(&raw[2..]).unwrap();
+            assert_eq!(
+                TransactionInner::from_bytes_strict(&data),
+                Ok(decode_tx(raw))
+            );
+
+            data.push(0);
+            assert_eq!(TransactionInner::from_bytes(&data), Ok(decode_tx(raw)));
+            assert_eq!(
+                TransactionInner::from_bytes_strict(&data),
+                Err(rlp::DecoderError::RlpInconsistentLengthAndData)
+            );
+        }
+        assert!(TransactionInner::from_bytes_strict(&[]).is_err());
+    }
+
+    #[test]
+    fn test_min_fork() {
+        assert_eq!(decode_tx(UNPROTECTED_TX).min_fork(), Fork::Frontier);
+        // EIP-155 signature
+        assert_eq!(decode_tx(LEGACY_TX).min_fork(), Fork::SpuriousDragon);
+        assert_eq!(decode_tx(ACCESS_LIST_TX).min_fork(), Fork::Berlin);
+        assert_eq!(decode_tx(DYNAMIC_FEE_TX).min_fork(), Fork::London);
+        assert!(Fork::SpuriousDragon < Fork::Berlin && Fork::Berlin < Fork::London);
+        assert!(Fork::Shanghai < Fork::Cancun);
+    }
+
+    #[test]
+    fn test_method_id() {
+        struct Registry(Vec<([u8; 4], &'static str)>);
+        impl MethodRegistry for Registry {
+            fn name(&self, id: &[u8; 4]) -> Option<&str> {
+                self.0
+                    .iter()
+                    .find(|(known, _)| known == id)
+                    .map(|(_, name)| *name)
+            }
+        }
+        let registry = Registry(vec![(
+            [0xa9, 0x05, 0x9c, 0xbb],
+            "transfer(address,uint256)",
+        )]);
+
+        let tx = decode_tx(ACCESS_LIST_TX);
+        assert_eq!(tx.method_id(), Some([0xa9, 0x05, 0x9c, 0xbb]));
+        assert_eq!(tx.method_name(&registry), Some("transfer(address,uint256)"));
+        assert_eq!(tx.method_name(&Registry(Vec::new())), None);
+
+        // no calldata, or init code instead of calldata
+        assert_eq!(decode_tx(LEGACY_TX).method_id(), None);
+        assert_eq!(decode_tx(CREATE_TX).method_id(), None);
+        assert_eq!(decode_tx(CREATE_TX).method_name(&registry), None);
+    }
+
+    #[test]
+    fn test_peek_tx_type() {
+        for (raw, ty) in [
+            (LEGACY_TX, 0),
+            (UNPROTECTED_TX, 0),
+            (ACCESS_LIST_TX, 1),
+            (DYNAMIC_FEE_TX, 2),
+        ] {
+            let data = hex::decode(&raw[2..]).unwrap();
+            assert_eq!(peek_tx_type(&data), Ok(ty));
+            // only the prefix is inspected
+            assert_eq!(peek_tx_type(&data[..1]), Ok(ty));
+        }
+        assert_eq!(peek_tx_type(&[0x03, 0xf8]), Ok(3));
+        assert_eq!(peek_tx_type(&[0x04]), Ok(4));
+        assert_eq!(peek_tx_type(&[]), Err(rlp::DecoderError::RlpIsTooShort));
+        for prefix in [0x00_u8, 0x05, 0x7e, 0xbf] {
+            assert!(peek_tx_type(&[prefix]).is_err(), "{:#x}", prefix);
+        }
+    }
+
+    #[test]
+    fn test_signing_hash() {
+        let chain_id = SU256::from(1_u64);
+        // the signing hash of the EIP-155 example
+        let tx = decode_tx(LEGACY_TX);
+        assert_eq!(
+            tx.signing_hash(&chain_id),
+            "0xdaf5a779ae972f972197303d7b574746c7ef83eadac0f2791ad23db92e4c8e53".into()
+        );
+
+        for raw in [LEGACY_TX, UNPROTECTED_TX, ACCESS_LIST_TX, DYNAMIC_FEE_TX] {
+            let tx = decode_tx(raw);
+            let hash = tx.signing_hash(&chain_id);
+            assert_eq!(signer().signing_hash(&tx), hash);
+            assert_eq!(hash, keccak_hash(&tx.sign_msg(&chain_id)).into());
+
+            // the signature was produced over the signing hash
+            let rec_sig = tx.signature(1);
+            let mut sig = [0_u8; 65];
+            sig[..32].copy_from_slice(&rec_sig.r);
+            sig[32..64].copy_from_slice(&rec_sig.s);
+            sig[64] = rec_sig.v;
+            let pubkey =
+                crypto::secp256k1_ecdsa_recover(&sig, hash.as_bytes().try_into().unwrap()).unwrap();
+            assert_eq!(
+                SH160::from_slice(&keccak_hash(&pubkey)[12..]),
+                SH160::from(SENDER),
+                "{}",
+                raw
+            );
+        }
+    }
+
+    #[test]
+    fn test_pre_sign_check() {
+        for raw in [
+            LEGACY_TX,
+            UNPROTECTED_TX,
+            ACCESS_LIST_TX,
+            DYNAMIC_FEE_TX,
+            CREATE_TX,
+        ] {
+            assert_eq!(decode_tx(raw).pre_sign_check(), Ok(()), "{}", raw);
+        }
+
+        let legacy = |f: &dyn Fn(&mut LegacyTx)| match decode_tx(LEGACY_TX) {
+            TransactionInner::Legacy(mut tx) => {
+                f(&mut tx);
+                TransactionInner::Legacy(tx).pre_sign_check()
+            }
+            _ => unreachable!(),
+        };
+        assert_eq!(
+            legacy(&|tx| tx.gas = 0_u64.into()),
+            Err("gas limit is zero".into())
+        );
+        assert_eq!(
+            legacy(&|tx| tx.gas_price = SU256::zero()),
+            Err("gas price is zero".into())
+        );
+        assert!(legacy(&|tx| tx.nonce = u64::MAX.into()).is_err());
+
+        let access_list = |f: &dyn Fn(&mut AccessListTx)| match decode_tx(ACCESS_LIST_TX) {
+            TransactionInner::AccessList(mut tx) => {
+                f(&mut tx);
+                TransactionInner::AccessList(tx).pre_sign_check()
+            }
+            _ => unreachable!(),
+        };
+        assert_eq!(
+            access_list(&|tx| tx.chain_id = SU256::zero()),
+            Err("chain id is zero".into())
+        );
+        assert_eq!(
+            access_list(&|tx| tx.gas_price = SU256::zero()),
+            Err("gas price is zero".into())
+        );
+
+        let dynamic_fee = |f: &dyn Fn(&mut DynamicFeeTx)| match decode_tx(DYNAMIC_FEE_TX) {
+            TransactionInner::DynamicFee(mut tx) => {
+                f(&mut tx);
+                TransactionInner::DynamicFee(tx).pre_sign_check()
+            }
+            _ => unreachable!(),
+        };
+        assert_eq!(
+            dynamic_fee(&|tx| tx.max_fee_per_gas = SU256::zero()),
+            Err("max fee per gas is zero".into())
+        );
+        assert!(dynamic_fee(
+            &|tx| tx.max_priority_fee_per_gas = tx.max_fee_per_gas + SU256::from(1_u64)
+        )
+        .unwrap_err()
+        .starts_with("max priority fee per gas higher"));
+        assert_eq!(
+            dynamic_fee(&|tx| tx.chain_id = SU256::zero()),
+            Err("chain id is zero".into())
+        );
+    }
+
+    #[test]
+    fn test_with_signature() {
+        // legacy signatures are always written with EIP-155 replay protection
+        for raw in [LEGACY_TX, ACCESS_LIST_TX, DYNAMIC_FEE_TX, CREATE_TX] {
+            let signed = decode_tx(raw);
+            let rec_sig = signed.signature(1);
+
+            // same fields as signed, but without a signature
+            let mut tx = signed.clone();
+            tx.with_signature(
+                Secp256k1RecoverableSignature {
+                    v: 0,
+                    r: [0_u8; 32],
+                    s: [0_u8; 32],
+                },
+                1,
+            );
+            assert_ne!(tx, signed);
+
+            tx.with_signature(rec_sig, 1);
+            assert_eq!(tx, signed, "{}", raw);
+            assert_eq!(tx.hash(), signed.hash());
+            assert_eq!(signer().try_sender(&tx), Ok(SENDER.into()));
+        }
+    }
+
+    #[test]
+    fn test_basic_validate() {
+        let london = ValidateOpts {
+            fork: Fork::London,
+            max_gas_limit: None,
+        };
+        for raw in [
+            LEGACY_TX,
+            UNPROTECTED_TX,
+            ACCESS_LIST_TX,
+            DYNAMIC_FEE_TX,
+            CREATE_TX,
+        ] {
+            assert_eq!(
+                decode_tx(raw).basic_validate(&signer(), london),
+                Ok(SENDER.into())
+            );
+        }
+
+        let tx = decode_tx(DYNAMIC_FEE_TX);
+        let berlin = ValidateOpts {
+            fork: Fork::Berlin,
+            ..london
+        };
+        assert_eq!(
+            tx.basic_validate(&signer(), berlin),
+            Err(ValidateError::UnsupportedType {
+                ty: 2,
+                fork: Fork::Berlin
+            })
+        );
+        let capped = ValidateOpts {
+            max_gas_limit: Some(20000),
+            ..london
+        };
+        assert_eq!(
+            tx.basic_validate(&signer(), capped),
+            Err(ValidateError::GasLimitExceeded {
+                max: 20000,
+                got: 21000
+            })
+        );
+        assert!(matches!(
+            tx.basic_validate(&Signer::new(SU256::from(5_u64)), london),
+            Err(ValidateError::Signer(SignerError::ChainIdMismatch { .. }))
+        ));
+        let mut tip_above_cap = tx;
+        if let TransactionInner::DynamicFee(tx) = &mut tip_above_cap {
+            tx.max_priority_fee_per_gas = tx.max_fee_per_gas + SU256::from(1_u64);
+        }
+        assert_eq!(
+            tip_above_cap.basic_validate(&signer(), london),
+            Err(ValidateError::TipAboveFeeCap)
+        );
+
+        let mut low_gas = decode_tx(LEGACY_TX);
+        let mut high_s = low_gas.clone();
+        if let TransactionInner::Legacy(tx) = &mut low_gas {
+            tx.gas = 20999_u64.into();
+        }
+        assert_eq!(
+            low_gas.basic_validate(&signer(), london),
+            Err(ValidateError::IntrinsicGasTooLow {
+                want: 21000,
+                got: 20999
+            })
+        );
+        if let TransactionInner::Legacy(tx) = &mut high_s {
+            let n: SU256 =
+                "0xfffffffffffffffffffffffffffffffebaaedce6af48a03bbfd25e8cd0364141".into();
+            tx.s = n - tx.s;
+        }
+        assert_eq!(
+            high_s.basic_validate(&signer(), london),
+            Err(ValidateError::HighS)
+        );
+
+        // the legacy v checks come from the signer
+        let legacy = decode_tx(LEGACY_TX);
+        assert_eq!(
+            legacy.basic_validate(&Signer::new(SU256::from(5_u64)), london),
+            Err(ValidateError::Signer(SignerError::ChainIdMismatch {
+                expect: 5_u64.into(),
+                got: 1_u64.into(),
+            }))
+        );
+        let mut bad_v = legacy;
+        if let TransactionInner::Legacy(tx) = &mut bad_v {
+            tx.v = 30_u64.into();
+        }
+        assert_eq!(
+            bad_v.basic_validate(&signer(), london),
+            Err(ValidateError::Signer(SignerError::InvalidSignature))
+        );
+    }
+
+    #[test]
+    fn test_intrinsic_gas() {
+        assert_eq!(decode_tx(LEGACY_TX).intrinsic_gas(Fork::London), 21000);
+        // `transfer` selector and one access list entry with one key
+        assert_eq!(
+            decode_tx(ACCESS_LIST_TX).intrinsic_gas(Fork::London),
+            21000 + 4 * 16 + 2400 + 1900
+        );
+        // 5 bytes of init code, 2 of them zero
+        let create = decode_tx(CREATE_TX);
+        assert_eq!(create.intrinsic_gas(Fork::Frontier), 21000 + 2 * 4 + 3 * 68);
+        assert_eq!(create.intrinsic_gas(Fork::London), 53000 + 2 * 4 + 3 * 16);
+        assert_eq!(
+            create.intrinsic_gas(Fork::Shanghai),
+            53000 + 2 * 4 + 3 * 16 + 2
+        );
+        assert_eq!(
+            create.intrinsic_gas(Fork::Cancun),
+            create.intrinsic_gas(Fork::Shanghai)
+        );
+    }
+
+    #[test]
+    fn test_pool_tx_from_rpc_transaction() {
+        for raw in [LEGACY_TX, DYNAMIC_FEE_TX] {
+            let mut tx = decode_tx(raw).to_transaction(None);
+            tx.from = None;
+            let recovered = PoolTx::from_rpc_transaction(tx.clone(), &signer()).unwrap();
+            assert_eq!(recovered.caller, SENDER.into());
+            assert_eq!(recovered.hash, tx.hash);
+            assert_eq!(*recovered.tx, decode_tx(raw));
+
+            tx.from = Some(SENDER.into());
+            let cached = PoolTx::from_rpc_transaction(tx, &signer()).unwrap();
+            assert_eq!(cached, PoolTx::with_tx(&signer(), decode_tx(raw)));
+        }
+
+        let mut tx = decode_tx(DYNAMIC_FEE_TX).to_transaction(None);
+        tx.max_fee_per_gas = None;
+        assert_eq!(
+            PoolTx::from_rpc_transaction(tx, &signer()),
+            Err(ConversionError::MissingFields)
+        );
+        let mut tx = decode_tx(DYNAMIC_FEE_TX).to_transaction(None);
+        tx.from = None;
+        assert!(matches!(
+            PoolTx::from_rpc_transaction(tx, &Signer::new(SU256::from(5_u64))),
+            Err(ConversionError::Signer(_))
+        ));
+
+        let mut tx = decode_tx(DYNAMIC_FEE_TX).to_transaction(None);
+        let hash = tx.hash;
+        tx.hash = SH256::default();
+        assert_eq!(
+            PoolTx::from_rpc_transaction(tx, &signer()),
+            Err(ConversionError::HashMismatch {
+                want: SH256::default(),
+                got: hash,
+            })
+        );
+        // `from` is trusted as is, without recovering the sender
+        let mut tx = decode_tx(LEGACY_TX).to_transaction(None);
+        tx.from = Some(SH160::default());
+        let pool_tx = PoolTx::from_rpc_transaction(tx, &Signer::new(SU256::from(5_u64))).unwrap();
+        assert_eq!(pool_tx.caller, SH160::default());
+    }
+
+    #[test]
+    fn test_is_replacement_of() {
+        let gwei = |n: u64| SU256::from(n * 1_000_000_000);
+        // tip of 2 gwei, max fee of 100 gwei
+        let old = decode_tx(DYNAMIC_FEE_TX);
+        let bump = |tip: SU256, cap: SU256, nonce: u64| match decode_tx(DYNAMIC_FEE_TX) {
+            TransactionInner::DynamicFee(mut tx) => {
+                tx.max_priority_fee_per_gas = tip;
+                tx.max_fee_per_gas = cap;
+                tx.nonce = nonce.into();
+                TransactionInner::DynamicFee(tx)
+            }
+            _ => unreachable!(),
+        };
+        let tip = SU256::from(2_200_000_000_u64);
+        assert!(bump(tip, gwei(110), 2).is_replacement_of(&old, 10));
+        assert!(!bump(tip, gwei(110), 2).is_replacement_of(&old, 11));
+        // both fields must be bumped
+        assert!(!bump(gwei(2), gwei(110), 2).is_replacement_of(&old, 10));
+        assert!(!bump(tip, gwei(109), 2).is_replacement_of(&old, 10));
+        assert!(!old.is_replacement_of(&old, 0));
+        assert!(!bump(tip, gwei(110), 3).is_replacement_of(&old, 10));
+
+        // legacy transactions bump the gas price, 20 gwei here
+        let old = decode_tx(LEGACY_TX);
+        let mut new = old.clone();
+        if let TransactionInner::Legacy(tx) = &mut new {
+            tx.gas_price = gwei(22);
+        }
+        assert!(new.is_replacement_of(&old, 10));
+        assert!(!new.is_replacement_of(&old, 11));
+    }
+
+    #[test]
+    fn test_access_list_rpc_form() {
+        use core::convert::TryFrom;
+        let key = "0x0000000000000000000000000000000000000000000000000000000000000001";
+        let json = serde_json::json!({
+            "address": "0x3535353535353535353535353535353535353535",
+            "storageKeys": [key],
+        });
+        let want = TransactionAccessTuple {
+            address: "0x3535353535353535353535353535353535353535".into(),
+            storage_keys: vec![key.into()],
+        };
+        assert_eq!(serde_json::to_value(&want).unwrap(), json);
+        assert_eq!(
+            serde_json::from_value::<TransactionAccessTuple>(json.clone()).unwrap(),
+            want
+        );
+        assert_eq!(TransactionAccessTuple::try_from(&json), Ok(want));
+
+        let short_key = serde_json::json!({
+            "address": "0x3535353535353535353535353535353535353535",
+            "storageKeys": ["0x01"],
+        });
+        assert!(TransactionAccessTuple::try_from(&short_key).is_err());
+        let short_address = serde_json::json!({"address": "0x35", "storageKeys": []});
+        assert!(TransactionAccessTuple::try_from(&short_address).is_err());
+        let missing = serde_json::json!({"address": "0x3535353535353535353535353535353535353535"});
+        assert_eq!(
+            TransactionAccessTuple::try_from(&missing),
+            Err("missing field storageKeys".into())
+        );
+        let not_hex = serde_json::json!({
+            "address": "0x3535353535353535353535353535353535353535",
+            "storageKeys": [1],
+        });
+        assert!(TransactionAccessTuple::try_from(&not_hex).is_err());
+    }
+
+    #[test]
+    fn test_access_tuple_from_str_list() {
+        let address = "0x3535353535353535353535353535353535353535";
+        let key1 = "0x0000000000000000000000000000000000000000000000000000000000000001";
+        let key2 = "0x0000000000000000000000000000000000000000000000000000000000000002";
+        let tuple = TransactionAccessTuple::from(vec![address, key1, key2]);
+        assert_eq!(tuple.address, address.into());
+        assert_eq!(tuple.storage_keys.len(), 2);
+        assert_eq!(
+            tuple.storage_keys,
+            vec![SH256::from(key1), SH256::from(key2)]
+        );
+
+        let tuple = TransactionAccessTuple::from(vec![address]);
+        assert!(tuple.storage_keys.is_empty());
+    }
+
+    #[test]
+    fn test_to_pending_json() {
+        let mut tx = decode_tx(LEGACY_TX).to_transaction(None);
+        tx.block_hash = Some(SH256::from([1_u8; 32]));
+        tx.block_number = Some(16_u64.into());
+        tx.transaction_index = Some(0_u64.into());
+        let mined = serde_json::to_value(&tx).unwrap();
+        assert_eq!(mined["blockNumber"], "0x10");
+        assert_eq!(mined["transactionIndex"], "0x0");
+
+        let pending = tx.to_pending_json();
+        for key in ["blockHash", "blockNumber", "transactionIndex"] {
+            assert!(mined.get(key).is_some(), "{}", key);
+            assert!(pending.get(key).is_none(), "{}", key);
+        }
+        let mut without = mined;
+        let obj = without.as_object_mut().unwrap();
+        obj.remove("blockHash");
+        obj.remove("blockNumber");
+        obj.remove("transactionIndex");
+        assert_eq!(pending, without);
+    }
+
+    #[test]
+    fn test_pack_transactions() {
+        let raws = [LEGACY_TX, ACCESS_LIST_TX, DYNAMIC_FEE_TX, CREATE_TX];
+        let txs: Vec<TransactionInner> =
+            (0..100).map(|i| decode_tx(raws[i % raws.len()])).collect();
+
+        let packed = pack_transactions(&txs);
+        assert_eq!(unpack_transactions(&packed), Ok(txs.clone()));
+
+        // stored one by one, each tx needs its own length prefix to be split
+        // again, while legacy txs are inlined in the packed list
+        let separate: usize = txs.iter().map(|tx| rlp::encode(&tx.to_bytes()).len()).sum();
+        assert!(packed.len() < separate, "{} >= {}", packed.len(), separate);
+
+        assert_eq!(unpack_transactions(&pack_transactions(&[])), Ok(Vec::new()));
+        assert!(unpack_transactions(&packed[..packed.len() - 1]).is_err());
+        let mut trailing = packed;
+        trailing.push(0);
+        assert!(unpack_transactions(&trailing).is_err());
+    }
+
+    #[test]
+    fn test_to_rpc_json() {
+        // keys of mainnet eth_getTransactionByHash responses from geth, without
+        // `transactionIndex`
+        let legacy_keys = [
+            "blockHash",
+            "blockNumber",
+            "chainId",
+            "from",
+            "gas",
+            "gasPrice",
+            "hash",
+            "input",
+            "nonce",
+            "r",
+            "s",
+            "to",
+            "type",
+            "v",
+            "value",
+        ];
+        let dynamic_fee_keys = [
+            "accessList",
+            "blockHash",
+            "blockNumber",
+            "chainId",
+            "from",
+            "gas",
+            "gasPrice",
+            "hash",
+            "input",
+            "maxFeePerGas",
+            "maxPriorityFeePerGas",
+            "nonce",
+            "r",
+            "s",
+            "to",
+            "type",
+            "v",
+            "value",
+            "yParity",
+        ];
+        let header = BlockHeader {
+            number: 16_u64.into(),
+            base_fee_per_gas: SU256::from(30_000_000_000_u64),
+            ..Default::default()
+        };
+        let keys =
+            |val: &Value| -> Vec<String> { val.as_object().unwrap().keys().cloned().collect() };
+
+        let json = decode_tx(LEGACY_TX).to_rpc_json(&signer(), Some(&header));
+        assert_eq!(keys(&json), legacy_keys);
+        assert_eq!(json["from"], SENDER);
+        assert_eq!(json["chainId"], "0x1");
+        assert_eq!(json["blockNumber"], "0x10");
+        assert_eq!(
+            json["blockHash"],
+            serde_json::to_value(header.hash()).unwrap()
+        );
+
+        let json = decode_tx(DYNAMIC_FEE_TX).to_rpc_json(&signer(), Some(&header));
+        assert_eq!(keys(&json), dynamic_fee_keys);
+        assert_eq!(json["from"], SENDER);
+        // base fee + tip
+        assert_eq!(json["gasPrice"], "0x773594000");
+
+        // signed for chain 5, v = 45
+        let raw = "0xf86c098504a817c800825208943535353535353535353535353535353535353535880de0b6b3a7640000802da0e5c7dd630ed41e9d6810494d80754a13cc65b6d7809fe33941b888995cc6ac88a06b9d520c3dfbba71a7d8e969669fb45ec046a8f90353a8313357746e59c40e24";
+        let json = decode_tx(raw).to_rpc_json(&signer(), None);
+        assert_eq!(json["chainId"], "0x5");
+        assert!(json.get("from").is_none());
+        let json = decode_tx(raw).to_rpc_json(&Signer::new(SU256::from(5_u64)), None);
+        assert_eq!(json["chainId"], "0x5");
+        assert_eq!(json["from"], SENDER);
+
+        // pre-EIP-155 transactions have no chain id
+        let json = decode_tx(UNPROTECTED_TX).to_rpc_json(&signer(), None);
+        assert!(json.get("chainId").is_none());
+        assert!(json.get("blockHash").is_none());
+        assert_eq!(json["from"], SENDER);
+    }
+
+    #[test]
+    fn test_transaction_json_matches_node() {
+        // eth_getTransactionByHash responses in the shape geth returns them
+        let legacy = serde_json::json!({
+            "blockHash": "0xdfcefc14a99ad4281b9caf951b9de727b23f24ac53bad00a8cf08cedc7946de9",
+            "blockNumber": "0x10",
+            "chainId": "0x1",
+            "from": SENDER,
+            "gas": "0x5208",
+            "gasPrice": "0x4a817c800",
+            "hash": "0x33469b22e9f636356c4160a87eb19df52b7412e8eac32a4a55ffe88ea8350788",
+            "input": "0x",
+            "nonce": "0x9",
+            "r": "0x28ef61340bd939bc2195fe537567866003e1a15d3c71ff63e1590620aa636276",
+            "s": "0x67cbe9d8997f761aecb703304b3800ccf555c9f3dc64214b297fb1966a3b6d83",
+            "to": "0x3535353535353535353535353535353535353535",
+            "transactionIndex": "0x0",
+            "type": "0x0",
+            "v": "0x25",
+            "value": "0xde0b6b3a7640000",
+        });
+        let dynamic_fee = serde_json::json!({
+            "accessList": [],
+            "blockHash": "0xdfcefc14a99ad4281b9caf951b9de727b23f24ac53bad00a8cf08cedc7946de9",
+            "blockNumber": "0x10",
+            "chainId": "0x1",
+            "from": SENDER,
+            "gas": "0x5208",
+            "gasPrice": "0x77359400",
+            "hash": "0x0f0ac1eed89575d4705e944343b49434abbc9154b6fae42e15e50148a1da9490",
+            "input": "0x",
+            "maxFeePerGas": "0x174876e800",
+            "maxPriorityFeePerGas": "0x77359400",
+            "nonce": "0x2",
+            "r": "0xab6d3920f1b94cb05a46e3d5a41cdc0648b8cd21ec625a17e99e7bfe1e184a39",
+            "s": "0x73928c1bbd20231b5e8c575a9023fe10626e28bb49326a24218561137e460d95",
+            "to": "0x3535353535353535353535353535353535353535",
+            "transactionIndex": "0x1",
+            "type": "0x2",
+            "v": "0x0",
+            "value": "0x1",
+            "yParity": "0x0",
+        });
+        for (raw, json) in [(LEGACY_TX, &legacy), (DYNAMIC_FEE_TX, &dynamic_fee)] {
+            let tx: Transaction = serde_json::from_value(json.clone()).unwrap();
+            assert_eq!(&serde_json::to_value(&tx).unwrap(), json);
+            assert_eq!(tx.inner(), Some(decode_tx(raw)));
+        }
+
+        let tx = decode_tx(LEGACY_TX).to_transaction(None);
+        let json = serde_json::to_value(&tx).unwrap();
+        for key in [
+            "maxFeePerGas",
+            "maxPriorityFeePerGas",
+            "accessList",
+            "yParity",
+            "blockHash",
+            "blockNumber",
+            "transactionIndex",
+        ] {
+            assert!(json.get(key).is_none(), "{}", key);
+        }
+        // contract creations keep `to` as null
+        let json = serde_json::to_value(decode_tx(CREATE_TX).to_transaction(None)).unwrap();
+        assert_eq!(json["to"], Value::Null);
+    }
+
+    #[test]
+    fn test_y_parity_only() {
+        let tx = decode_tx(DYNAMIC_FEE_TX).to_transaction(None);
+        let mut json = serde_json::to_value(&tx).unwrap();
+        json.as_object_mut().unwrap().remove("v");
+        let got: Transaction = serde_json::from_value(json.clone()).unwrap();
+        assert_eq!(got.y_parity, Some(0_u64.into()));
+        let inner = got.inner().unwrap();
+        assert_eq!(inner, decode_tx(DYNAMIC_FEE_TX));
+        assert_eq!(inner.y_parity(), Some(0));
+        assert_eq!(signer().sender(&inner), SENDER.into());
+
+        // `yParity` wins over a bogus `v`
+        json["v"] = "0x1b".into();
+        let got: Transaction = serde_json::from_value(json).unwrap();
+        assert_eq!(got.inner(), Some(decode_tx(DYNAMIC_FEE_TX)));
+
+        assert_eq!(decode_tx(LEGACY_TX).y_parity(), None);
+        assert_eq!(
+            decode_tx(ACCESS_LIST_TX).y_parity().map(|p| p <= 1),
+            Some(true)
+        );
+    }
+
+    #[test]
+    fn test_pool_tx_from_bytes_wallet_parity() {
+        let tx = decode_tx(DYNAMIC_FEE_TX);
+        let mut rlp = PoolTx::with_tx(&signer(), tx.clone()).to_rlp();
+        let mut tampered = tx;
+        if let TransactionInner::DynamicFee(tx) = &mut tampered {
+            tx.v = (tx.v.as_u64() + 27).into();
+        }
+        rlp.tx = rlp::encode(&tampered).to_vec().into();
+        assert_eq!(
+            PoolTx::from_bytes(&signer(), &rlp::encode(&rlp)).unwrap_err(),
+            rlp::DecoderError::Custom("invalid tx signature")
+        );
+    }
+}
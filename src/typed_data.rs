@@ -0,0 +1,359 @@
+use std::prelude::v1::*;
+
+use crate::{parse_string_u256, recover_address, SH160, SH256};
+use crypto::keccak_hash;
+use ethereum_types::U256;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::{BTreeMap, BTreeSet};
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Eip712Error {
+    UnknownType(String),
+    MissingField(String),
+    InvalidValue { ty: String, value: String },
+    InvalidSignature,
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize, PartialEq, Eq)]
+pub struct TypedDataField {
+    pub name: String,
+    pub r#type: String,
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct TypedData {
+    pub types: BTreeMap<String, Vec<TypedDataField>>,
+    pub primary_type: String,
+    pub domain: Value,
+    pub message: Value,
+}
+
+impl TypedData {
+    pub fn encode_type(&self, primary: &str) -> Result<String, Eip712Error> {
+        let mut deps = BTreeSet::new();
+        self.find_dependencies(primary, &mut deps)?;
+        deps.remove(primary);
+
+        let mut out = String::new();
+        for name in std::iter::once(primary).chain(deps.iter().map(|n| n.as_str())) {
+            let fields: Vec<String> = self
+                .fields(name)?
+                .iter()
+                .map(|field| format!("{} {}", field.r#type, field.name))
+                .collect();
+            out.push_str(name);
+            out.push('(');
+            out.push_str(&fields.join(","));
+            out.push(')');
+        }
+        Ok(out)
+    }
+
+    pub fn type_hash(&self, ty: &str) -> Result<SH256, Eip712Error> {
+        Ok(keccak_hash(self.encode_type(ty)?.as_bytes()).into())
+    }
+
+    pub fn encode_data(&self, ty: &str, value: &Value) -> Result<Vec<u8>, Eip712Error> {
+        let fields = self.fields(ty)?;
+        let mut out = Vec::with_capacity((fields.len() + 1) * 32);
+        out.extend_from_slice(self.type_hash(ty)?.as_bytes());
+        for field in fields {
+            let val = value
+                .get(field.name.as_str())
+                .ok_or_else(|| Eip712Error::MissingField(field.name.clone()))?;
+            out.extend_from_slice(&self.encode_field(&field.r#type, val)?);
+        }
+        Ok(out)
+    }
+
+    pub fn hash_struct(&self, ty: &str, value: &Value) -> Result<SH256, Eip712Error> {
+        Ok(keccak_hash(&self.encode_data(ty, value)?).into())
+    }
+
+    pub fn domain_separator(&self) -> Result<SH256, Eip712Error> {
+        self.hash_struct("EIP712Domain", &self.domain)
+    }
+
+    pub fn signing_hash(&self) -> Result<SH256, Eip712Error> {
+        let mut data = Vec::with_capacity(66);
+        data.extend_from_slice(&[0x19, 0x01]);
+        data.extend_from_slice(self.domain_separator()?.as_bytes());
+        if self.primary_type != "EIP712Domain" {
+            data.extend_from_slice(
+                self.hash_struct(&self.primary_type, &self.message)?
+                    .as_bytes(),
+            );
+        }
+        Ok(keccak_hash(&data).into())
+    }
+
+    pub fn recover(&self, sig: &[u8; 65]) -> Result<SH160, Eip712Error> {
+        let hash = self.signing_hash()?;
+        recover_address(&hash, sig).ok_or(Eip712Error::InvalidSignature)
+    }
+
+    fn fields(&self, ty: &str) -> Result<&[TypedDataField], Eip712Error> {
+        self.types
+            .get(ty)
+            .map(|fields| fields.as_slice())
+            .ok_or_else(|| Eip712Error::UnknownType(ty.into()))
+    }
+
+    fn find_dependencies(&self, ty: &str, found: &mut BTreeSet<String>) -> Result<(), Eip712Error> {
+        let ty = base_type(ty);
+        if found.contains(ty) || !self.types.contains_key(ty) {
+            return Ok(());
+        }
+        found.insert(ty.into());
+        for field in self.fields(ty)? {
+            self.find_dependencies(&field.r#type, found)?;
+        }
+        Ok(())
+    }
+
+    fn encode_field(&self, ty: &str, value: &Value) -> Result<[u8; 32], Eip712Error> {
+        if let Some(idx) = ty.rfind('[') {
+            let items = value.as_array().ok_or_else(|| invalid(ty, value))?;
+            let mut data = Vec::with_capacity(items.len() * 32);
+            for item in items {
+                data.extend_from_slice(&self.encode_field(&ty[..idx], item)?);
+            }
+            return Ok(keccak_hash(&data));
+        }
+        if self.types.contains_key(ty) {
+            return Ok(keccak_hash(&self.encode_data(ty, value)?));
+        }
+
+        let mut out = [0_u8; 32];
+        match ty {
+            "string" => {
+                let s = value.as_str().ok_or_else(|| invalid(ty, value))?;
+                out = keccak_hash(s.as_bytes());
+            }
+            "bytes" => {
+                out = keccak_hash(&parse_bytes(ty, value)?);
+            }
+            "bool" => {
+                let b = value.as_bool().ok_or_else(|| invalid(ty, value))?;
+                out[31] = b as u8;
+            }
+            "address" => {
+                let addr = parse_bytes(ty, value)?;
+                if addr.len() != 20 {
+                    return Err(invalid(ty, value));
+                }
+                out[12..].copy_from_slice(&addr);
+            }
+            _ if ty.starts_with("uint") => {
+                out = parse_int(ty, value, false)?.into();
+            }
+            _ if ty.starts_with("int") => {
+                out = parse_int(ty, value, true)?.into();
+            }
+            _ if ty.starts_with("bytes") => {
+                let size: usize = ty[5..].parse().map_err(|_| invalid(ty, value))?;
+                let data = parse_bytes(ty, value)?;
+                if size == 0 || size > 32 || data.len() > size {
+                    return Err(invalid(ty, value));
+                }
+                out[..data.len()].copy_from_slice(&data);
+            }
+            _ => return Err(Eip712Error::UnknownType(ty.into())),
+        }
+        Ok(out)
+    }
+}
+
+fn base_type(ty: &str) -> &str {
+    match ty.find('[') {
+        Some(idx) => &ty[..idx],
+        None => ty,
+    }
+}
+
+fn invalid(ty: &str, value: &Value) -> Eip712Error {
+    Eip712Error::InvalidValue {
+        ty: ty.into(),
+        value: value.to_string(),
+    }
+}
+
+fn parse_bytes(ty: &str, value: &Value) -> Result<Vec<u8>, Eip712Error> {
+    let s = value.as_str().ok_or_else(|| invalid(ty, value))?;
+    hex::decode(s.trim_start_matches("0x")).map_err(|_| invalid(ty, value))
+}
+
+fn parse_int(ty: &str, value: &Value, signed: bool) -> Result<U256, Eip712Error> {
+    let (negative, abs) = match value {
+        Value::Number(n) => match (n.as_u64(), n.as_i64()) {
+            (Some(n), _) => (false, U256::from(n)),
+            (None, Some(n)) => (true, U256::from(n.unsigned_abs())),
+            _ => return Err(invalid(ty, value)),
+        },
+        Value::String(s) => {
+            let (negative, s) = match s.strip_prefix('-') {
+                Some(s) => (true, s),
+                None => (false, s.as_str()),
+            };
+            let abs = parse_string_u256(s).map_err(|_| invalid(ty, value))?;
+            (negative, abs)
+        }
+        _ => return Err(invalid(ty, value)),
+    };
+    if !negative {
+        return Ok(abs);
+    }
+    if !signed {
+        return Err(invalid(ty, value));
+    }
+    // two's complement
+    Ok((!abs).overflowing_add(U256::one()).0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    // the example from EIP-712
+    fn mail() -> TypedData {
+        serde_json::from_value(json!({
+            "types": {
+                "EIP712Domain": [
+                    {"name": "name", "type": "string"},
+                    {"name": "version", "type": "string"},
+                    {"name": "chainId", "type": "uint256"},
+                    {"name": "verifyingContract", "type": "address"}
+                ],
+                "Person": [
+                    {"name": "name", "type": "string"},
+                    {"name": "wallet", "type": "address"}
+                ],
+                "Mail": [
+                    {"name": "from", "type": "Person"},
+                    {"name": "to", "type": "Person"},
+                    {"name": "contents", "type": "string"}
+                ]
+            },
+            "primaryType": "Mail",
+            "domain": {
+                "name": "Ether Mail",
+                "version": "1",
+                "chainId": 1,
+                "verifyingContract": "0xCcCCccccCCCCcCCCCCCcCcCccCcCCCcCcccccccC"
+            },
+            "message": {
+                "from": {
+                    "name": "Cow",
+                    "wallet": "0xCD2a3d9F938E13CD947Ec05AbC7FE734Df8DD826"
+                },
+                "to": {
+                    "name": "Bob",
+                    "wallet": "0xbBbBBBBbbBBBbbbBbbBbbbbBBbBbbbbBbBbbBBbB"
+                },
+                "contents": "Hello, Bob!"
+            }
+        }))
+        .unwrap()
+    }
+
+    #[test]
+    fn test_mail_example() {
+        let data = mail();
+        assert_eq!(
+            data.encode_type("Mail").unwrap(),
+            "Mail(Person from,Person to,string contents)Person(string name,address wallet)"
+        );
+        assert_eq!(
+            data.type_hash("Mail").unwrap(),
+            "0xa0cedeb2dc280ba39b857546d74f5549c3a1d7bdc2dd96bf881f76108e23dac2".into()
+        );
+        assert_eq!(
+            data.domain_separator().unwrap(),
+            "0xf2cee375fa42b42143804025fc449deafd50cc031ca257e0b194a650a912090f".into()
+        );
+        assert_eq!(
+            data.hash_struct("Mail", &data.message).unwrap(),
+            "0xc52c0ee5d84264471806290a3f2c4cecfc5490626bf912d01f240d7a274b371e".into()
+        );
+        assert_eq!(
+            data.signing_hash().unwrap(),
+            "0xbe609aee343fb3c4b28e1df9e632fca64fcfaede20f02e86244efddf30957bd2".into()
+        );
+
+        let mut sig = [0_u8; 65];
+        sig.copy_from_slice(&hex::decode("4355c47d63924e8a72e509b65029052eb6c299d53a04e167c5775fd466751c9d07299936d304c153f6443dfa05f40ff007d72911b6f72307f996231605b915621c").unwrap());
+        assert_eq!(
+            data.recover(&sig).unwrap(),
+            "0xcd2a3d9f938e13cd947ec05abc7fe734df8dd826".into()
+        );
+    }
+
+    #[test]
+    fn test_encode_type_dependencies() {
+        let data: TypedData = serde_json::from_value(json!({
+            "types": {
+                "Transaction": [
+                    {"name": "to", "type": "Person[]"},
+                    {"name": "asset", "type": "Asset"}
+                ],
+                "Person": [{"name": "wallet", "type": "address"}],
+                "Asset": [
+                    {"name": "owner", "type": "Person"},
+                    {"name": "amount", "type": "uint256"}
+                ],
+                "Unused": [{"name": "x", "type": "bool"}]
+            },
+            "primaryType": "Transaction",
+            "domain": {},
+            "message": {}
+        }))
+        .unwrap();
+        // the primary type first, then the referenced types sorted by name
+        assert_eq!(
+            data.encode_type("Transaction").unwrap(),
+            "Transaction(Person[] to,Asset asset)Asset(Person owner,uint256 amount)Person(address wallet)"
+        );
+        assert_eq!(
+            data.encode_type("Missing"),
+            Err(Eip712Error::UnknownType("Missing".into()))
+        );
+    }
+
+    #[test]
+    fn test_encode_int() {
+        let data: TypedData = serde_json::from_value(json!({
+            "types": {
+                "Value": [
+                    {"name": "a", "type": "int256"},
+                    {"name": "b", "type": "int8"},
+                    {"name": "c", "type": "uint256"}
+                ]
+            },
+            "primaryType": "Value",
+            "domain": {},
+            "message": {}
+        }))
+        .unwrap();
+        let encoded = data
+            .encode_data("Value", &json!({"a": -1, "b": "-2", "c": "0x10"}))
+            .unwrap();
+        assert_eq!(&encoded[32..64], &[0xff_u8; 32][..]);
+        let mut minus_two = [0xff_u8; 32];
+        minus_two[31] = 0xfe;
+        assert_eq!(&encoded[64..96], &minus_two[..]);
+        let mut sixteen = [0_u8; 32];
+        sixteen[31] = 0x10;
+        assert_eq!(&encoded[96..128], &sixteen[..]);
+
+        assert!(data
+            .encode_data("Value", &json!({"a": 1, "b": 1, "c": -1}))
+            .is_err());
+        assert_eq!(
+            data.encode_data("Value", &json!({"a": 1, "b": 1})),
+            Err(Eip712Error::MissingField("c".into()))
+        );
+    }
+}